@@ -0,0 +1,40 @@
+//! Time β-reductions whose argument is large but returned as is or discarded
+//!
+//! Run with `cargo bench`.
+
+use std::{collections::HashMap, hint::black_box, time::Instant};
+
+use lamda_calc::{
+    eval::{reduce_step, Strategy},
+    parser::{abs, app, var, Term},
+};
+
+const ITERATIONS: usize = 1_000;
+
+fn bench(name: &str, term: &Term) {
+    let env = HashMap::new();
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(reduce_step(black_box(term), &env, Strategy::Normal));
+    }
+    println!(
+        "{:<10} {:?} per step",
+        name,
+        start.elapsed() / ITERATIONS as u32
+    );
+}
+
+fn main() {
+    // Argument with 2^16 leaves that all mention `z`
+    let mut big = var("z");
+    for _ in 0..16 {
+        big = app(big.clone(), big);
+    }
+    bench("identity", &app(abs("x", var("x")), big.clone()));
+    bench("constant", &app(abs("x", var("y")), big.clone()));
+    bench("unused", &app(abs("x", abs("z", var("y"))), big.clone()));
+    bench(
+        "used",
+        &app(abs("x", abs("z", app(var("x"), var("z")))), big),
+    );
+}
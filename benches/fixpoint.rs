@@ -0,0 +1,35 @@
+//! Time normalizing a term that unfolds a long chain of definitions one at a time
+//!
+//! Each definition is only reachable after the previous one was unfolded, so every
+//! step inlines variables into the whole term. Checking whether that changed the term
+//! must not compare the growing term with its previous version.
+//! Run with `cargo bench`.
+
+use std::{collections::HashMap, hint::black_box, time::Instant};
+
+use lamda_calc::{
+    eval::{reduce_bounded, Strategy, DEFAULT_STEP_LIMIT},
+    parser::{abs, app, var},
+};
+
+const ITERATIONS: u32 = 20;
+
+fn main() {
+    // D0 = z and Dk = λy.(y Dk-1), so `x D300` takes a δ-step per definition
+    let mut env = HashMap::new();
+    env.insert("D0".to_string(), var("z"));
+    for k in 1..=300 {
+        let prev = var(&format!("D{}", k - 1));
+        env.insert(format!("D{}", k), abs("y", app(var("y"), prev)));
+    }
+    let term = app(var("x"), var("D300"));
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(reduce_bounded(&term, &env, Strategy::Normal, DEFAULT_STEP_LIMIT).unwrap());
+    }
+    println!(
+        "{:<10} {:?} per normalization",
+        "chain",
+        start.elapsed() / ITERATIONS
+    );
+}
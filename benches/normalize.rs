@@ -0,0 +1,55 @@
+//! Time normalizing a chain of applications whose leading arguments are already normal
+//!
+//! Steps from scratch search the normal arguments for a redex again every time,
+//! while a reduction remembers where its last redex was and skips them.
+//! Run with `cargo bench`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    hint::black_box,
+    time::Instant,
+};
+
+use lamda_calc::{
+    eval::{beta_reduce, reduce_bounded, Strategy, DEFAULT_STEP_LIMIT},
+    parser::{abs, app, var, Term},
+};
+
+const ITERATIONS: u32 = 20;
+
+fn bench(name: &str, normalize: impl Fn() -> Term) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(normalize());
+    }
+    println!(
+        "{:<10} {:?} per normalization",
+        name,
+        start.elapsed() / ITERATIONS
+    );
+}
+
+fn main() {
+    // x N N ... N (I (I ... (I z))) with large normal arguments N
+    let numeral = abs(
+        "f",
+        abs("x", (0..30).fold(var("x"), |t, _| app(var("f"), t))),
+    );
+    let mut term = (0..20).fold(var("x"), |t, _| app(t, numeral.clone()));
+    let redexes = (0..200).fold(var("z"), |t, _| app(abs("i", var("i")), t));
+    term = app(term, redexes);
+    let env = HashMap::new();
+    bench("fresh", || {
+        let mut term = term.clone();
+        loop {
+            let next = beta_reduce(&term, &env, HashSet::new());
+            if next == term {
+                return term;
+            }
+            term = next;
+        }
+    });
+    bench("marked", || {
+        reduce_bounded(&term, &env, Strategy::Normal, DEFAULT_STEP_LIMIT).unwrap()
+    });
+}
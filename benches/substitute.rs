@@ -0,0 +1,39 @@
+//! Time substituting a value under many nested abstractions
+//!
+//! The free variables of the value decide whether a binder must be renamed, and they are
+//! the same at every abstraction, so a substitution collects them only once.
+//! Run with `cargo bench`.
+
+use std::{hint::black_box, time::Instant};
+
+use lamda_calc::{
+    eval::substitute,
+    parser::{abs, app, var},
+    stats::collect,
+};
+
+const ITERATIONS: u32 = 100;
+const DEPTH: usize = 1000;
+
+fn main() {
+    // Value with 2^10 leaves, substituted under the binders λa0.λa1...(x a0)
+    let mut value = var("z");
+    for _ in 0..10 {
+        value = app(value.clone(), value);
+    }
+    let term = (0..DEPTH).rev().fold(app(var("x"), var("a0")), |body, i| {
+        abs(&format!("a{}", i), body)
+    });
+    let (_, stats) = collect(|| substitute(&term, "x", &value));
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(substitute(black_box(&term), "x", &value));
+    }
+    println!(
+        "{:<10} {:?} per substitution, {} free variable scans for {} abstractions",
+        "nested",
+        start.elapsed() / ITERATIONS,
+        stats.free_var_scans,
+        DEPTH
+    );
+}
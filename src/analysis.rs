@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    error::Error,
+    eval::{free_vars, substitute, Env, DEFAULT_STEP_LIMIT},
+    parser::{Expr, Term},
+    visit::walk,
+};
+
+/// Map each definition in the environment to the other definitions it references
+/// A definition depends on a name if that name is both free in its value and bound in `env`.
+pub fn env_dependencies(env: &Env) -> HashMap<String, HashSet<String>> {
+    env.iter()
+        .map(|(name, term)| {
+            let deps = free_vars(term)
+                .into_iter()
+                .filter(|v| v != name && env.contains_key(v))
+                .collect();
+            (name.clone(), deps)
+        })
+        .collect()
+}
+
+/// Describe the dependencies between definitions as a Graphviz DOT digraph
+/// Every definition is a node with an edge to each definition it references,
+/// listed in alphabetical order so the output is stable.
+pub fn env_graph_dot(env: &Env) -> String {
+    let mut deps = env_dependencies(env).into_iter().collect::<Vec<_>>();
+    deps.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut dot = String::from("digraph env {\n");
+    for (name, _) in &deps {
+        dot += &format!("    {:?};\n", name);
+    }
+    for (name, uses) in deps {
+        let mut uses = uses.into_iter().collect::<Vec<_>>();
+        uses.sort();
+        for used in uses {
+            dot += &format!("    {:?} -> {:?};\n", name, used);
+        }
+    }
+    dot + "}"
+}
+
+/// Check that no definition of a program uses a name that is only defined after it
+/// Definitions are stored unreduced, so such a forward reference would silently stay a free
+/// variable when the program is used before the later definition is made.
+/// A definition may refer to itself, and names that the program never defines are left to
+/// the unbound variable warnings. Fails with `Error::DefinitionOrder` on the first offender.
+pub fn check_definition_order(prog: &[Expr]) -> Result<(), Error> {
+    let mut later = prog
+        .iter()
+        .filter_map(|expr| match expr {
+            Expr::Assignment(name, _) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut defined = HashSet::new();
+    for expr in prog {
+        let Expr::Assignment(name, value) = expr else {
+            continue;
+        };
+        later.remove(later.iter().position(|v| v == name).unwrap());
+        let mut forward = free_vars(value)
+            .into_iter()
+            .filter(|v| v != name && !defined.contains(v) && later.contains(&v.as_str()))
+            .collect::<Vec<_>>();
+        forward.sort();
+        if let Some(used) = forward.into_iter().next() {
+            return Err(Error::DefinitionOrder {
+                name: name.clone(),
+                used,
+            });
+        }
+        defined.insert(name.clone());
+    }
+    Ok(())
+}
+
+/// Check if a definition refers back to itself, directly or through other definitions
+pub fn is_recursive(name: &str, env: &Env) -> bool {
+    let mut seen = HashSet::new();
+    let mut stack = env
+        .get(name)
+        .map(free_vars)
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    while let Some(v) = stack.pop() {
+        if v == name {
+            return true;
+        }
+        if let Some(value) = env.get(&v) {
+            if seen.insert(v) {
+                stack.extend(free_vars(value));
+            }
+        }
+    }
+    false
+}
+
+/// Heuristically check if reducing a term might not terminate
+/// Flags self-applications `x x` of a binder that is applied to an argument,
+/// such as the omega combinator `(λx.(x x)) (λx.(x x))`.
+/// This is not a decision procedure and never prevents evaluation.
+pub fn might_diverge(term: &Term) -> bool {
+    /// Check if `var` is applied to itself in `term` without being shadowed
+    fn self_applies(term: &Term, var: &str) -> bool {
+        match term {
+            Term::Variable(_) => false,
+            Term::Abstraction(param, _) if param == var => false,
+            Term::Abstraction(_, body) => self_applies(body, var),
+            Term::Application(f, x) => match (f.as_ref(), x.as_ref()) {
+                (Term::Variable(f), Term::Variable(x)) if f == var && x == var => true,
+                (f, x) => self_applies(f, var) || self_applies(x, var),
+            },
+        }
+    }
+    match term {
+        Term::Variable(_) => false,
+        Term::Abstraction(_, body) => might_diverge(body),
+        Term::Application(f, x) => {
+            matches!(f.as_ref(), Term::Abstraction(param, body) if self_applies(body, param))
+                || might_diverge(f)
+                || might_diverge(x)
+        }
+    }
+}
+
+/// Check if a term has no free variables
+/// Names defined in an environment count as free unless they are inlined first.
+pub fn is_closed(term: &Term) -> bool {
+    free_vars(term).is_empty()
+}
+
+/// Count the free occurrences of `var` in a term
+pub fn occurrences(term: &Term, var: &str) -> usize {
+    match term {
+        Term::Variable(v) => usize::from(v == var),
+        Term::Abstraction(param, _) if param == var => 0,
+        Term::Abstraction(_, body) => occurrences(body, var),
+        Term::Application(f, x) => occurrences(f, var) + occurrences(x, var),
+    }
+}
+
+/// Estimate how many times arguments are copied when reducing a term in normal order
+/// Each β-reduction `(λx.body) arg` copies `arg` once for every occurrence of `x` in `body`
+/// beyond the first, which is work that call-by-need evaluation would share.
+/// Reduction stops after `DEFAULT_STEP_LIMIT` steps for terms without a normal form.
+pub fn duplication_report(term: &Term) -> usize {
+    /// Contract the leftmost-outermost redex, returning the new term and the copies made
+    fn contract(term: &Term) -> Option<(Term, usize)> {
+        match term {
+            Term::Variable(_) => None,
+            Term::Abstraction(param, body) => {
+                let (body, copies) = contract(body)?;
+                Some((Term::Abstraction(param.clone(), Box::new(body)), copies))
+            }
+            Term::Application(f, x) => {
+                if let Term::Abstraction(param, body) = f.as_ref() {
+                    let copies = occurrences(body, param).saturating_sub(1);
+                    return Some((substitute(body, param, x), copies));
+                }
+                if let Some((f, copies)) = contract(f) {
+                    return Some((Term::Application(Box::new(f), x.clone()), copies));
+                }
+                let (x, copies) = contract(x)?;
+                Some((Term::Application(f.clone(), Box::new(x)), copies))
+            }
+        }
+    }
+    let mut term = term.clone();
+    let mut total = 0;
+    for _ in 0..DEFAULT_STEP_LIMIT {
+        let Some((next, copies)) = contract(&term) else {
+            break;
+        };
+        total += copies;
+        term = next;
+    }
+    total
+}
+
+/// Number of nodes in a term, counting every variable, abstraction and application
+pub fn term_size(term: &Term) -> usize {
+    let mut size = 0;
+    walk(term, &mut |_| size += 1);
+    size
+}
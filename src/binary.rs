@@ -0,0 +1,157 @@
+use crate::{error::Error, parser::Term};
+
+/// Tags identifying the term variant that follows in the binary format
+const TAG_VARIABLE: u8 = 0;
+const TAG_ABSTRACTION: u8 = 1;
+const TAG_APPLICATION: u8 = 2;
+
+/// Error produced when bytes do not encode a term
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The input ended in the middle of a term
+    UnexpectedEnd,
+    /// A byte that is not a known term tag
+    InvalidTag(u8),
+    /// A variable name that is not valid UTF-8
+    InvalidName,
+    /// A name length whose LEB128 encoding does not fit in a `usize`
+    LengthOverflow,
+    /// Bytes left over after a complete term
+    TrailingBytes(usize),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::InvalidTag(tag) => write!(f, "invalid term tag {}", tag),
+            DecodeError::InvalidName => write!(f, "variable name is not valid UTF-8"),
+            DecodeError::LengthOverflow => write!(f, "name length is too large"),
+            DecodeError::TrailingBytes(n) => write!(f, "{} trailing bytes after term", n),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Serialize a term into a compact binary format
+///
+/// Each term is a tag byte followed by its fields in prefix order,
+/// names are stored as a LEB128 length followed by their UTF-8 bytes.
+pub fn to_bytes(term: &Term) -> Vec<u8> {
+    fn write_name(name: &str, out: &mut Vec<u8>) {
+        let mut len = name.len();
+        while len >= 0x80 {
+            out.push((len as u8 & 0x7f) | 0x80);
+            len >>= 7;
+        }
+        out.push(len as u8);
+        out.extend_from_slice(name.as_bytes());
+    }
+    fn write(term: &Term, out: &mut Vec<u8>) {
+        match term {
+            Term::Variable(v) => {
+                out.push(TAG_VARIABLE);
+                write_name(v, out);
+            }
+            Term::Abstraction(param, body) => {
+                out.push(TAG_ABSTRACTION);
+                write_name(param, out);
+                write(body, out);
+            }
+            Term::Application(f, x) => {
+                out.push(TAG_APPLICATION);
+                write(f, out);
+                write(x, out);
+            }
+        }
+    }
+    let mut out = Vec::new();
+    write(term, &mut out);
+    out
+}
+
+/// Deserialize a term written by `to_bytes`
+/// Terms are decoded with an explicit stack, so deeply nested input does not overflow
+/// the call stack.
+pub fn from_bytes(bytes: &[u8]) -> Result<Term, Error> {
+    struct Reader<'a> {
+        bytes: &'a [u8],
+    }
+    impl Reader<'_> {
+        fn byte(&mut self) -> Result<u8, DecodeError> {
+            let (&b, rest) = self.bytes.split_first().ok_or(DecodeError::UnexpectedEnd)?;
+            self.bytes = rest;
+            Ok(b)
+        }
+
+        fn name(&mut self) -> Result<String, DecodeError> {
+            let mut len = 0usize;
+            let mut shift = 0;
+            loop {
+                let b = self.byte()?;
+                let part = (b & 0x7f) as usize;
+                if shift >= usize::BITS || (part << shift) >> shift != part {
+                    return Err(DecodeError::LengthOverflow);
+                }
+                len |= part << shift;
+                if b & 0x80 == 0 {
+                    break;
+                }
+                shift += 7;
+            }
+            if len > self.bytes.len() {
+                return Err(DecodeError::UnexpectedEnd);
+            }
+            let (name, rest) = self.bytes.split_at(len);
+            self.bytes = rest;
+            String::from_utf8(name.to_vec()).map_err(|_| DecodeError::InvalidName)
+        }
+
+        fn term(&mut self) -> Result<Term, DecodeError> {
+            // Terms waiting for their subterms to be decoded
+            enum Frame {
+                Abstraction(String),
+                Function,
+                Argument(Term),
+            }
+            let mut stack = Vec::new();
+            loop {
+                let mut term = match self.byte()? {
+                    TAG_VARIABLE => Term::Variable(self.name()?),
+                    TAG_ABSTRACTION => {
+                        stack.push(Frame::Abstraction(self.name()?));
+                        continue;
+                    }
+                    TAG_APPLICATION => {
+                        stack.push(Frame::Function);
+                        continue;
+                    }
+                    tag => return Err(DecodeError::InvalidTag(tag)),
+                };
+                // Complete the terms whose last subterm was decoded
+                loop {
+                    match stack.pop() {
+                        None => return Ok(term),
+                        Some(Frame::Abstraction(param)) => {
+                            term = Term::Abstraction(param, Box::new(term));
+                        }
+                        Some(Frame::Function) => {
+                            stack.push(Frame::Argument(term));
+                            break;
+                        }
+                        Some(Frame::Argument(f)) => {
+                            term = Term::Application(Box::new(f), Box::new(term));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let mut reader = Reader { bytes };
+    let term = reader.term()?;
+    match reader.bytes.len() {
+        0 => Ok(term),
+        n => Err(DecodeError::TrailingBytes(n).into()),
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::VecDeque;
+
+use crate::{eval::Env, warning::Warning};
+
+/// Default number of results kept by a `ResultCache`
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// Printed result of a REPL input and the warnings it produced
+pub type CachedResult = (String, Vec<Warning>);
+
+/// Least recently used cache of REPL results keyed by the trimmed input
+/// Results are only valid for the environment they were computed in,
+/// so all entries are dropped as soon as the environment changes.
+#[derive(Debug)]
+pub struct ResultCache {
+    capacity: usize,
+    env: Env,
+    /// Entries ordered from least to most recently used
+    entries: VecDeque<(String, CachedResult)>,
+}
+
+impl ResultCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            env: Env::new(),
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Invalidate all entries if `env` differs from the one they were computed in
+    fn sync(&mut self, env: &Env) {
+        if &self.env != env {
+            self.env = env.clone();
+            self.entries.clear();
+        }
+    }
+
+    /// Look up the result of an input, marking it as the most recently used
+    pub fn get(&mut self, input: &str, env: &Env) -> Option<CachedResult> {
+        self.sync(env);
+        let i = self
+            .entries
+            .iter()
+            .position(|(key, _)| key == input.trim())?;
+        let entry = self.entries.remove(i)?;
+        self.entries.push_back(entry);
+        self.entries.back().map(|(_, result)| result.clone())
+    }
+
+    /// Store the result of an input, evicting the least recently used entry if full
+    pub fn insert(&mut self, input: &str, env: &Env, result: CachedResult) {
+        self.sync(env);
+        self.entries.retain(|(key, _)| key != input.trim());
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        if self.capacity > 0 {
+            self.entries.push_back((input.trim().to_string(), result));
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{eval::Env, token::KEYWORDS};
+
+/// Check if a character can be part of a variable name or keyword
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '\''
+}
+
+/// Complete the word ending at byte offset `pos` of `line` with defined names and keywords
+/// Returns the offset the word starts at and the sorted candidates it is a prefix of.
+/// Matching is case-sensitive, so `fac` completes `factorial` but not `Fact`.
+pub fn complete(line: &str, pos: usize, env: &Env) -> (usize, Vec<String>) {
+    let start = line[..pos]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(pos, |(i, _)| i);
+    let prefix = &line[start..pos];
+    let mut candidates = env
+        .keys()
+        .map(String::as_str)
+        .chain(KEYWORDS)
+        .filter(|name| name.starts_with(prefix))
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
@@ -0,0 +1,262 @@
+use std::{
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use clap::{parser::ValueSource, Arg, CommandFactory, Parser};
+
+use crate::{
+    error::Error,
+    eval::{Strategy, DEFAULT_STEP_LIMIT},
+    parser::DEFAULT_MAX_NESTING,
+    pattern::RewriteRule,
+    print::{ColorChoice, Style},
+};
+
+/// Command line configuration for the interpreter
+#[derive(Parser, Debug, Clone, PartialEq, Default)]
+#[command(
+    name = "lambda",
+    version,
+    about = "Lambda calculus interpreter",
+    after_help = "If no file or program is given, the program will run in REPL mode"
+)]
+pub struct Config {
+    /// File to read lambda calculus program from, or `-` to read it from standard input
+    pub file: Option<String>,
+    /// Evaluate the given program instead of reading a file
+    #[arg(short, long = "eval", value_name = "PROGRAM", conflicts_with = "file")]
+    pub eval: Option<String>,
+    /// Definition to evaluate after the program, applied to the terms given after `--`
+    #[arg(long, value_name = "NAME")]
+    pub entry: Option<String>,
+    /// Arguments the `--entry` definition is applied to, from left to right
+    #[arg(last = true, value_name = "ARGS", requires = "entry")]
+    pub args: Vec<String>,
+    /// File to load as prelude instead of the built-in standard library
+    #[arg(long, value_name = "FILE")]
+    pub prelude_path: Option<PathBuf>,
+    /// Do not load any prelude at startup
+    #[arg(long, conflicts_with = "prelude_path")]
+    pub no_prelude: bool,
+    /// Print the parsed AST of the program in debug form without evaluating it
+    #[arg(long)]
+    pub ast_only: bool,
+    /// Check that the grammar parses a set of built-in programs and exit
+    #[arg(long)]
+    pub grammar_check: bool,
+    /// Print the dependencies between definitions as a Graphviz DOT graph and exit
+    #[arg(long)]
+    pub dump_env_graph: bool,
+    /// Write the reduction of the last term of the program to an HTML page, step by step
+    #[arg(long, value_name = "FILE")]
+    pub trace_html: Option<PathBuf>,
+    /// Print debug information
+    #[arg(short, long)]
+    pub verbose: bool,
+    /// Only print the final result, without warnings
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Print each reduction step labelled with the rule applied
+    #[arg(long)]
+    pub explain: bool,
+    /// Print the number of reduction steps taken after every result
+    #[arg(long)]
+    pub show_steps: bool,
+    /// Print the number of substitutions, renames and term allocations after each program
+    #[arg(long)]
+    pub stats: bool,
+    /// Print the time spent parsing, inlining definitions and reducing to stderr after each program
+    #[arg(long)]
+    pub profile: bool,
+    /// Print the normal form of every reducible subterm of each term before its result
+    #[arg(long)]
+    pub subterm_normal_forms: bool,
+    /// Normalize the values of definitions when they are defined instead of when used
+    #[arg(long)]
+    pub eager_defs: bool,
+    /// Store the values of definitions unreduced and normalize them only when used [default]
+    ///
+    /// Overrides `eager_defs = true` in a project file, so `Y`-based definitions can be stored.
+    #[arg(long, conflicts_with = "eager_defs")]
+    pub lazy_defs: bool,
+    /// Check that normal and applicative order reduce each term to the same normal form
+    #[arg(long)]
+    pub verify: bool,
+    /// Stop reducing a term after this many milliseconds
+    #[arg(long, value_name = "MS")]
+    pub repl_eval_timeout: Option<u64>,
+    /// Treat names that are not defined as opaque constants, without warning about them
+    #[arg(long)]
+    pub opaque_free_vars: bool,
+    /// Stop reducing a term after this many steps, also bounding `assert`, `--verify`, `--eager-defs` and `:defeq`
+    #[arg(long, value_name = "N")]
+    pub step_limit: Option<usize>,
+    /// Stop reducing a term once it grows larger than this many nodes
+    #[arg(long, value_name = "N")]
+    pub max_term_size: Option<usize>,
+    /// Reduction strategy used to evaluate terms
+    #[arg(short, long, value_enum, default_value_t)]
+    pub strategy: Strategy,
+    /// Reject applications of more than two terms without parentheses
+    #[arg(long)]
+    pub strict_app_arity: bool,
+    /// Reject programs with parentheses or abstractions nested deeper than this [default: 256]
+    ///
+    /// Printed Church numerals above 254 nest deeper than the default.
+    #[arg(long, value_name = "N")]
+    pub max_nesting: Option<usize>,
+    /// When to color the output
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t)]
+    pub color: ColorChoice,
+    /// Notation used to print terms
+    #[arg(long, value_enum, default_value_t, alias = "format")]
+    pub style: Style,
+    /// Truncate printed results after this many characters
+    #[arg(long, value_name = "N")]
+    pub max_output_size: Option<usize>,
+    /// Print results that equal a definition by the defined name
+    #[arg(long)]
+    pub use_aliases: bool,
+    /// Print results that are Church numerals or booleans as literals like `3` or `true`
+    #[arg(long)]
+    pub decode: bool,
+    /// Print results reproducibly: normal order, canonical bound variable names and no colors
+    #[arg(long, conflicts_with = "strategy")]
+    pub canonical: bool,
+    /// Rename bound variables in results to single letters, reusing them in sibling scopes
+    #[arg(long, conflicts_with = "canonical")]
+    pub short_names: bool,
+    /// Prefix results that are abstractions with their number of leading binders, like `<fn/2>`
+    #[arg(long)]
+    pub show_arity: bool,
+    /// Print applications of several arguments like `f a b` instead of `((f a) b)`
+    #[arg(long, conflicts_with = "abbreviate")]
+    pub flat_spines: bool,
+    /// Abbreviate repeated applications like `(f (f (f x)))` as `(f^3 x)`
+    #[arg(long)]
+    pub abbreviate: bool,
+    /// Rewrite rules declared by the program being run, tried before β-reduction
+    #[arg(skip)]
+    pub rules: Vec<RewriteRule>,
+    /// File to load and persist REPL history in
+    #[arg(long, value_name = "FILE")]
+    pub repl_history: Option<PathBuf>,
+}
+
+/// Project file in the working directory whose options are used as defaults
+pub const PROJECT_FILE: &str = "lambda.toml";
+
+impl Config {
+    /// Read the options set in a project file, as pairs of option name and value
+    ///
+    /// Every line sets an option `name = value`, named like the field of `Config` or the
+    /// long flag, where strings may be quoted and flags are set with `true` or `false`.
+    /// Blank lines and comments starting with `#` are skipped.
+    pub fn project_options(source: &str) -> Result<Vec<(String, String)>, Error> {
+        let command = Config::command();
+        let mut options = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let error = |message: String| Error::Project {
+                line: i + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                return Err(error(format!("expected `name = value`, found `{}`", line)));
+            };
+            let name = name.trim().replace('-', "_");
+            let value = value.trim().trim_matches('"').to_string();
+            let Some(arg) = command
+                .get_arguments()
+                .find(|arg| arg.get_id() == name.as_str() && arg.get_long().is_some())
+            else {
+                return Err(error(format!("unknown option `{}`", name)));
+            };
+            if !arg.get_action().takes_values() && value != "true" && value != "false" {
+                return Err(error(format!("`{}` must be `true` or `false`", name)));
+            }
+            options.push((name, value));
+        }
+        Ok(options)
+    }
+
+    /// Parse command line arguments, using the project `options` for those not given
+    /// Options that conflict with one given on the command line are left out of the defaults.
+    /// The first argument is the binary name, as for `Config::try_parse_from`.
+    pub fn try_parse_with_project(
+        options: &[(String, String)],
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Config, clap::Error> {
+        let args = args.into_iter().collect::<Vec<_>>();
+        let mut command = Config::command();
+        command.build();
+        let matches = command.clone().try_get_matches_from(&args)?;
+        let given = command
+            .get_arguments()
+            .filter(|arg| {
+                matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+            .collect::<Vec<_>>();
+        // An option given on the command line replaces the project option and any it conflicts with
+        let overridden = |arg: &Arg| {
+            given.iter().any(|given| {
+                given.get_id() == arg.get_id()
+                    || command.get_arg_conflicts_with(given).contains(&arg)
+                    || command.get_arg_conflicts_with(arg).contains(given)
+            })
+        };
+        let mut defaults = Vec::new();
+        for (name, value) in options {
+            let Some(arg) = command
+                .get_arguments()
+                .find(|arg| arg.get_id() == name.as_str())
+            else {
+                continue;
+            };
+            if overridden(arg) {
+                continue;
+            }
+            let long = arg.get_long().unwrap_or_default();
+            match arg.get_action().takes_values() {
+                true => defaults.push(format!("--{}={}", long, value)),
+                false if value == "true" => defaults.push(format!("--{}", long)),
+                false => {}
+            }
+        }
+        let (binary, rest) = args
+            .split_first()
+            .map_or((None, &[][..]), |(b, r)| (Some(b), r));
+        Config::try_parse_from(binary.into_iter().chain(&defaults).chain(rest))
+    }
+
+    /// Read the program given with `-e` or as a file, where the file `-` is read from `stdin`
+    /// Returns `None` if no program is given. Errors are prefixed with the name of the file.
+    pub fn read_program(&self, mut stdin: impl Read) -> Option<io::Result<String>> {
+        if let Some(program) = &self.eval {
+            return Some(Ok(program.clone()));
+        }
+        let (name, source) = match self.file.as_deref()? {
+            "-" => {
+                let mut source = String::new();
+                let read = stdin.read_to_string(&mut source).map(|_| source);
+                ("standard input", read)
+            }
+            file => (file, std::fs::read_to_string(file)),
+        };
+        Some(source.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", name, e))))
+    }
+
+    /// Maximum number of steps for reductions that must end, such as assertions and `--verify`
+    pub fn step_limit(&self) -> usize {
+        self.step_limit.unwrap_or(DEFAULT_STEP_LIMIT)
+    }
+
+    /// Maximum depth of nested parentheses accepted in programs
+    pub fn max_nesting(&self) -> usize {
+        self.max_nesting.unwrap_or(DEFAULT_MAX_NESTING)
+    }
+}
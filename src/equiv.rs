@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::is_recursive,
+    eval::{eta_reduce, free_vars, inline_vars, normal_form, reduce_bounded, Env, Strategy},
+    parser::Term,
+};
+
+/// Check if two terms are equal up to renaming of bound variables
+///
+/// See https://en.wikipedia.org/wiki/Lambda_calculus#%CE%B1-conversion.
+pub fn alpha_eq(a: &Term, b: &Term) -> bool {
+    /// Compare terms with the binders currently in scope on each side
+    fn eq<'a>(a: &'a Term, b: &'a Term, scope: &mut Vec<(&'a str, &'a str)>) -> bool {
+        match (a, b) {
+            (Term::Variable(x), Term::Variable(y)) => {
+                // The innermost binder of each name decides if they are bound together
+                let bx = scope.iter().rposition(|(l, _)| *l == x);
+                let by = scope.iter().rposition(|(_, r)| *r == y);
+                match (bx, by) {
+                    (None, None) => x == y,
+                    (bx, by) => bx == by,
+                }
+            }
+            (Term::Abstraction(x, e1), Term::Abstraction(y, e2)) => {
+                scope.push((x, y));
+                let result = eq(e1, e2, scope);
+                scope.pop();
+                result
+            }
+            (Term::Application(f1, x1), Term::Application(f2, x2)) => {
+                eq(f1, f2, scope) && eq(x1, x2, scope)
+            }
+            _ => false,
+        }
+    }
+    eq(a, b, &mut Vec::new())
+}
+
+/// Rename every bound variable after the depth of its binder, as `a`, `b`, ... `z`, `a1`, ...
+/// Names of free variables are skipped, so α-equivalent terms get identical names.
+pub fn canonicalize_names(term: &Term) -> Term {
+    rename_by_depth(term, &('a'..='z').collect::<Vec<_>>())
+}
+
+/// Rename every bound variable to a single letter, `a` to `z` and then `A` to `Z`
+/// Like `canonicalize_names`, binders are named after their depth so sibling scopes
+/// reuse letters, and only terms nested deeper than 52 binders get longer names.
+pub fn short_names(term: &Term) -> Term {
+    rename_by_depth(term, &('a'..='z').chain('A'..='Z').collect::<Vec<_>>())
+}
+
+/// Name binders after their depth using the letters of `alphabet`, then with numbered letters
+/// Names of free variables are skipped, so the result is α-equivalent to `term`.
+fn rename_by_depth(term: &Term, alphabet: &[char]) -> Term {
+    /// Rename with the names of the binders in scope, `names[d]` for depth `d`
+    fn rename<'a>(
+        term: &'a Term,
+        scope: &mut Vec<(&'a str, usize)>,
+        names: &mut Vec<String>,
+        alphabet: &[char],
+        free: &HashSet<String>,
+    ) -> Term {
+        match term {
+            Term::Variable(v) => match scope.iter().rev().find(|(name, _)| name == v) {
+                Some((_, depth)) => Term::Variable(names[*depth].clone()),
+                None => term.clone(),
+            },
+            Term::Abstraction(param, body) => {
+                let depth = scope.len();
+                let mut i = 0;
+                while names.len() <= depth {
+                    let letter = alphabet[i % alphabet.len()];
+                    let name = match i / alphabet.len() {
+                        0 => letter.to_string(),
+                        n => format!("{}{}", letter, n),
+                    };
+                    if !free.contains(&name) && !names.contains(&name) {
+                        names.push(name);
+                    }
+                    i += 1;
+                }
+                scope.push((param, depth));
+                let body = rename(body, scope, names, alphabet, free);
+                scope.pop();
+                Term::Abstraction(names[depth].clone(), Box::new(body))
+            }
+            Term::Application(f, x) => Term::Application(
+                Box::new(rename(f, scope, names, alphabet, free)),
+                Box::new(rename(x, scope, names, alphabet, free)),
+            ),
+        }
+    }
+    let free = free_vars(term);
+    rename(term, &mut Vec::new(), &mut Vec::new(), alphabet, &free)
+}
+
+/// Check if two terms are equal after unfolding the definitions in `env`
+/// Both terms are normalized within `limit` steps and compared up to α-equivalence.
+/// Terms that do not normalize within the budget are considered unequal.
+pub fn definitional_eq(a: &Term, b: &Term, env: &Env, limit: usize) -> bool {
+    let a = reduce_bounded(&inline_vars(a, env), env, Strategy::Normal, limit).ok();
+    let b = reduce_bounded(&inline_vars(b, env), env, Strategy::Normal, limit).ok();
+    match (a, b) {
+        (Some(a), Some(b)) => alpha_eq(&a, &b),
+        _ => false,
+    }
+}
+
+/// Find the definitions in `env` whose value is definitionally equal to a term
+/// Values are normalized like in `definitional_eq`, skipping those that do not
+/// normalize within `limit` steps. Recursive definitions are skipped without reducing them,
+/// since unfolding them never ends. Returns the matching names in alphabetical order.
+pub fn search(term: &Term, env: &Env, limit: usize) -> Vec<String> {
+    let normalize =
+        |t: &Term| reduce_bounded(&inline_vars(t, env), env, Strategy::Normal, limit).ok();
+    let Some(query) = normalize(term) else {
+        return Vec::new();
+    };
+    let mut names = env
+        .iter()
+        .filter(|(name, _)| !is_recursive(name, env))
+        .filter(|(_, value)| normalize(value).is_some_and(|value| alpha_eq(&query, &value)))
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// Check if two terms are equal up to α-equivalence after normalizing them to βη-normal form
+/// Both terms are β-normalized within `limit` steps each and then η-reduced,
+/// so `λx.(f x)` equals `f`. Returns `None` if either term exceeds the budget.
+pub fn beta_eta_eq(a: &Term, b: &Term, limit: usize) -> Option<bool> {
+    let a = normal_form(a, limit)?;
+    let b = normal_form(b, limit)?;
+    Some(alpha_eq(&eta_reduce(&a), &eta_reduce(&b)))
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use crate::{
+    binary::DecodeError,
+    eval::RedexPath,
+    numeral::NUMERAL_CAP,
+    parser::{ParseError, Term},
+    print::term_plain,
+};
+
+/// Failures reported by the library
+#[derive(Debug)]
+pub enum Error {
+    /// Input does not match the grammar
+    Parse(ParseError),
+    /// A name that was required to be defined is not
+    UnboundVar(String),
+    /// A term was not normalized within the step limit
+    StepLimit { steps: usize },
+    /// A term was not normalized within the time limit, in milliseconds
+    Timeout { millis: u64 },
+    /// A term grew larger than the maximum number of nodes during reduction
+    TermTooLarge { limit: usize },
+    /// A path that does not lead to a β-redex of a term
+    NotARedex(RedexPath),
+    /// The sides of an `assert` statement are not equal after normalization
+    AssertionFailed(Term, Term),
+    /// A numeral literal does not fit in a machine integer
+    NumeralOverflow,
+    /// A term to evaluate uses a numeral literal larger than `NUMERAL_CAP`
+    NumeralCap(usize),
+    /// A definition uses a name that is defined after it
+    DefinitionOrder { name: String, used: String },
+    /// Files that import each other, in import order starting and ending with the same file
+    ImportCycle(Vec<String>),
+    /// A line of a project file such as `lambda.toml` that does not set a known option
+    Project { line: usize, message: String },
+    /// Bytes that do not encode a term
+    Decode(DecodeError),
+    /// Reading or writing a file failed
+    Io(std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Parse(e) => write!(f, "{}", e),
+            Error::UnboundVar(name) => write!(f, "`{}` is not defined", name),
+            Error::StepLimit { steps } => {
+                write!(f, "no normal form was reached within {} steps", steps)
+            }
+            Error::Timeout { millis } => write!(f, "evaluation timed out after {} ms", millis),
+            Error::TermTooLarge { limit } => {
+                write!(f, "term grew larger than {} nodes", limit)
+            }
+            Error::NotARedex(path) => write!(f, "no β-redex at path {:?}", path),
+            Error::AssertionFailed(lhs, rhs) => write!(
+                f,
+                "assertion failed: `{}` is not equal to `{}`",
+                term_plain(lhs),
+                term_plain(rhs)
+            ),
+            Error::NumeralOverflow => write!(f, "numeral literal is too large"),
+            Error::NumeralCap(n) => write!(
+                f,
+                "numeral literal `{}` is larger than {} and is not reduced",
+                n, NUMERAL_CAP
+            ),
+            Error::DefinitionOrder { name, used } => {
+                write!(f, "`{}` uses `{}` before it is defined", name, used)
+            }
+            Error::ImportCycle(paths) => write!(f, "import cycle: {}", paths.join(" -> ")),
+            Error::Project { line, message } => write!(f, "line {}: {}", line, message),
+            Error::Decode(e) => write!(f, "invalid binary term: {}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Parse(e) => Some(e.as_ref()),
+            Error::Decode(e) => Some(e),
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::Parse(e)
+    }
+}
+
+impl From<pest::error::Error<crate::parser::Rule>> for Error {
+    fn from(e: pest::error::Error<crate::parser::Rule>) -> Self {
+        Error::Parse(Box::new(e))
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Self {
+        Error::Decode(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
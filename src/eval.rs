@@ -1,47 +1,115 @@
 use std::{
-    borrow::Borrow,
-    collections::{HashMap, HashSet},
+    borrow::{Borrow, Cow},
+    cell::OnceCell,
+    collections::{BTreeSet, HashMap, HashSet},
+    time::{Duration, Instant},
 };
 
 use crate::{
-    parser::{parse_prog, Expr, Program, Term},
+    analysis::{might_diverge, occurrences, term_size},
+    config::Config,
+    equiv::{alpha_eq, definitional_eq},
+    error::Error,
+    numeral::{numeral_literal, unfold_numeral, NUMERAL_CAP},
+    parser::{
+        check_app_arity, check_nesting, parse_prog, parse_statements, parse_term_str, preprocess,
+        Expr, Term,
+    },
+    pattern::{rewrite_step, RewriteRule},
     print,
+    profile::{self, Phase, Profile},
+    stats::{self, Stats},
+    warning::{Warning, WarningKind},
 };
 
 /// Environment mapping variable names to terms
-type Env = HashMap<String, Term>;
+pub type Env = HashMap<String, Term>;
+
+/// Order in which redexes are chosen when reducing a term
+///
+/// See https://en.wikipedia.org/wiki/Reduction_strategy#Lambda_calculus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Strategy {
+    /// Leftmost-outermost redex first, reducing under abstractions
+    #[default]
+    Normal,
+    /// Leftmost-innermost redex first, reducing under abstractions
+    Applicative,
+    /// Call-by-value: arguments are reduced before substitution, never under abstractions
+    Cbv,
+    /// Call-by-name: arguments are substituted unreduced, never under abstractions
+    Cbn,
+}
+
+/// Default number of reduction steps allowed when normalizing under a budget
+pub const DEFAULT_STEP_LIMIT: usize = 10_000;
 
 /// Substitute a variable in a term with another term
 /// This is used in β-reduction.
 ///
 /// See https://en.wikipedia.org/wiki/Lambda_calculus#Substitution.
 pub fn substitute(term: &Term, var: &str, value: &Term) -> Term {
+    substitute_scanned(term, var, value, &OnceCell::new())
+}
+
+/// Substitute like `substitute`, where `value_vars` caches the free variables of `value`
+/// They are only needed under abstractions and are the same for the whole substitution,
+/// so they are collected at most once instead of at every abstraction.
+fn substitute_scanned(
+    term: &Term,
+    var: &str,
+    value: &Term,
+    value_vars: &OnceCell<HashSet<String>>,
+) -> Term {
+    let scan = |value| {
+        stats::record(|s| s.free_var_scans += 1);
+        free_vars(value)
+    };
+    stats::record(|s| s.substitutions += 1);
     match term {
         // var[var := value] = value
-        Term::Variable(v) if v == var => value.clone(),
+        Term::Variable(v) if v == var => {
+            stats::record(|s| s.allocations += term_size(value));
+            value.clone()
+        }
         // x[var := value] = x   (x != var)
-        Term::Variable(_) => term.clone(),
+        Term::Variable(_) => {
+            stats::record(|s| s.allocations += 1);
+            term.clone()
+        }
         // (e1 e2)[var := value] = (e1[var := value]) (e2[var := value])
-        Term::Application(e1, e2) => Term::Application(
-            Box::new(substitute(e1, var, value)),
-            Box::new(substitute(e2, var, value)),
-        ),
+        Term::Application(e1, e2) => {
+            stats::record(|s| s.allocations += 1);
+            Term::Application(
+                Box::new(substitute_scanned(e1, var, value, value_vars)),
+                Box::new(substitute_scanned(e2, var, value, value_vars)),
+            )
+        }
         // (λx. e)[var := value] = λx. e  (x == var)
-        Term::Abstraction(s, _) if s == var => term.clone(), // Bound variable, no substitution needed
+        Term::Abstraction(s, _) if s == var => {
+            // Bound variable, no substitution needed
+            stats::record(|s| s.allocations += term_size(term));
+            term.clone()
+        }
         // (λx. e)[var := value] = λx. e  (x in free_vars(value))
-        Term::Abstraction(s, body) if free_vars(value).contains(s) => {
+        Term::Abstraction(s, body) if value_vars.get_or_init(|| scan(value)).contains(s) => {
             // Avoid variable capture collisions by generating a fresh variable name
-            let mut s_new = s.clone();
-            while free_vars(value).contains(&s_new) {
-                s_new.push('\'');
-            }
-            let new_body = substitute(&rename_var(body, s, &s_new), var, value);
+            stats::record(|s| {
+                s.renames += 1;
+                s.allocations += 1;
+            });
+            let s_new = fresh_var(s, value_vars.get().unwrap());
+            let new_body = substitute_scanned(&rename_var(body, s, &s_new), var, value, value_vars);
             Term::Abstraction(s_new, Box::new(new_body))
         }
         // (λx. e)[var := value] = λx. e[var := value]  (x != var and x not in free_vars(value))
         Term::Abstraction(s, body) => {
             // Substitute inside the abstraction's body
-            Term::Abstraction(s.clone(), Box::new(substitute(body, var, value)))
+            stats::record(|s| s.allocations += 1);
+            Term::Abstraction(
+                s.clone(),
+                Box::new(substitute_scanned(body, var, value, value_vars)),
+            )
         }
     }
 }
@@ -49,31 +117,84 @@ pub fn substitute(term: &Term, var: &str, value: &Term) -> Term {
 /// Collect free variables in a term
 ///
 /// See https://en.wikipedia.org/wiki/Lambda_calculus#Free_and_bound_variables.
+///
+/// Traverses the term with an explicit stack instead of recursion,
+/// so that very deep terms do not overflow the call stack.
 pub fn free_vars(term: &Term) -> HashSet<String> {
-    match term {
-        // free_vars(x) = {x}
-        Term::Variable(s) => {
-            let mut set = HashSet::new();
-            set.insert(s.clone());
-            set
-        }
-        // free_vars(λx. e) = free_vars(e) - {x}
-        Term::Abstraction(s, body) => {
-            let mut set = free_vars(body);
-            set.remove(s);
-            set
+    enum Visit<'a> {
+        Term(&'a Term),
+        /// Leave the scope of a binder
+        Unbind(&'a str),
+    }
+    let mut set = HashSet::new();
+    // Number of enclosing binders for each bound variable name
+    let mut bound: HashMap<&str, usize> = HashMap::new();
+    let mut stack = vec![Visit::Term(term)];
+    while let Some(visit) = stack.pop() {
+        match visit {
+            // free_vars(x) = {x}
+            Visit::Term(Term::Variable(s)) => {
+                if !bound.contains_key(s.as_str()) {
+                    set.insert(s.clone());
+                }
+            }
+            // free_vars(λx. e) = free_vars(e) - {x}
+            Visit::Term(Term::Abstraction(s, body)) => {
+                *bound.entry(s).or_default() += 1;
+                stack.push(Visit::Unbind(s));
+                stack.push(Visit::Term(body));
+            }
+            // free_vars(e1 e2) = free_vars(e1) + free_vars(e2)
+            Visit::Term(Term::Application(e1, e2)) => {
+                stack.push(Visit::Term(e2));
+                stack.push(Visit::Term(e1));
+            }
+            Visit::Unbind(s) => {
+                if let Some(n) = bound.get_mut(s) {
+                    *n -= 1;
+                    if *n == 0 {
+                        bound.remove(s);
+                    }
+                }
+            }
         }
-        // free_vars(e1 e2) = free_vars(e1) + free_vars(e2)
-        Term::Application(e1, e2) => {
-            let mut set = free_vars(e1);
-            set.extend(free_vars(e2));
-            set
+    }
+    set
+}
+
+/// Length up to which fresh names are generated by appending primes
+const MAX_PRIMED_LEN: usize = 8;
+
+/// Generate a variable name based on `base` that is not in `avoid`
+/// by appending primes until it is unique. Once the name would grow longer than
+/// `MAX_PRIMED_LEN`, a numeric suffix `x_1`, `x_2`, ... is used instead, replacing
+/// the primes or suffix of `base`, so freshening a fresh name does not compound.
+///
+/// Candidates are tried in this fixed order and `avoid` is only queried for membership,
+/// never iterated, so the name depends on the contents of `avoid` but not on its
+/// iteration order. Renaming the same term thus gives the same names in every run.
+pub fn fresh_var(base: &str, avoid: &HashSet<String>) -> String {
+    let mut name = base.to_string();
+    while avoid.contains(&name) {
+        if name.len() >= MAX_PRIMED_LEN {
+            let stem = base.trim_end_matches('\'');
+            let stem = match stem.rsplit_once('_') {
+                Some((stem, n)) if !stem.is_empty() && n.parse::<usize>().is_ok() => stem,
+                _ => stem,
+            };
+            return (1..)
+                .map(|n| format!("{}_{}", stem, n))
+                .find(|name| !avoid.contains(name))
+                .unwrap();
         }
+        name.push('\'');
     }
+    name
 }
 
 // Rename a variable in a term
 pub fn rename_var(term: &Term, old_var: &str, new_var: &str) -> Term {
+    stats::record(|s| s.allocations += 1);
     match term {
         Term::Variable(s) if s == old_var => Term::Variable(new_var.to_string()),
         Term::Variable(_) => term.clone(),
@@ -92,125 +213,1295 @@ pub fn rename_var(term: &Term, old_var: &str, new_var: &str) -> Term {
     }
 }
 
-// Perform β-reduction on a lambda calculus term
-pub fn beta_reduce(term: &Term, env: &Env, mut bound_vars: HashSet<String>) -> Term {
+/// Reduction rule that was applied in a reduction step
+#[derive(Debug, Clone, PartialEq)]
+pub enum Explanation {
+    /// β-reduction of `(λparam.body) arg` into `body[param := arg]`
+    Beta {
+        param: String,
+        body: Term,
+        arg: Term,
+    },
+    /// δ-reduction unfolding the env definition of a name
+    Delta(String),
+    /// Rewriting a subterm with a rule declared by `rule lhs => rhs`
+    Rewrite,
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Explanation::Beta { param, body, arg } => {
+                let redex = Term::Application(
+                    Box::new(Term::Abstraction(param.clone(), Box::new(body.clone()))),
+                    Box::new(arg.clone()),
+                );
+                write!(
+                    f,
+                    "β: {} → {}[{}:={}]",
+                    print::term(&redex),
+                    print::term(body),
+                    print::var(param),
+                    print::term(arg)
+                )
+            }
+            Explanation::Delta(name) => write!(f, "δ: unfold `{}`", name),
+            Explanation::Rewrite => write!(f, "rule: rewrite a matching subterm"),
+        }
+    }
+}
+
+/// Rules applied in a reduction step, only recorded when they are explained
+struct Log(Option<Vec<Explanation>>);
+
+impl Log {
+    fn push(&mut self, rule: impl FnOnce() -> Explanation) {
+        if let Some(rules) = &mut self.0 {
+            rules.push(rule());
+        }
+    }
+}
+
+/// Contract a redex, recording the β-reduction in `log`
+/// Identity functions and unused parameters are contracted without substituting,
+/// so large arguments are not traversed when they are returned as is or discarded.
+fn contract(param: &str, body: &Term, arg: &Term, log: &mut Log) -> Term {
+    log.push(|| Explanation::Beta {
+        param: param.to_string(),
+        body: body.clone(),
+        arg: arg.clone(),
+    });
+    match body {
+        // (λx.x) e = e
+        Term::Variable(v) if v == param => arg.clone(),
+        // (λx.body) e = body  (x not in free_vars(body))
+        _ if occurrences(body, param) == 0 => body.clone(),
+        _ => substitute(body, param, arg),
+    }
+}
+
+/// Direction from a term to one of its subterms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dir {
+    /// Body of an abstraction
+    Body,
+    /// Function of an application
+    Fun,
+    /// Argument of an application
+    Arg,
+}
+
+/// Path from a term to one of its β-redexes
+pub type RedexPath = Vec<Dir>;
+
+/// Contract the leftmost-outermost redex, or `None` if there is none
+/// The rules applied are appended to `log` and the path to the redex is pushed onto `path`.
+///
+/// `hint` is the path to the redex contracted in the previous step of the same reduction.
+/// The function of an application on it that was passed to reach the argument is marked
+/// normal, since it had no redex and the contraction did not change it.
+fn normal_step(
+    term: &Term,
+    env: &Env,
+    bound_vars: &mut HashSet<String>,
+    log: &mut Log,
+    hint: &[Dir],
+    path: &mut Vec<Dir>,
+) -> Option<Term> {
+    // Continue along the previous path if it passes through this subterm in direction `dir`
+    let follow = |dir: Dir| match hint {
+        [first, rest @ ..] if *first == dir => rest,
+        _ => &[],
+    };
     match term {
-        Term::Variable(_) => term.clone(),
+        Term::Variable(_) => None,
         Term::Abstraction(var, body) => {
-            bound_vars.insert(var.clone());
-            Term::Abstraction(var.clone(), Box::new(beta_reduce(body, env, bound_vars)))
+            // Reduce inside the body with the parameter bound, restoring an outer binding after
+            let shadowed = !bound_vars.insert(var.clone());
+            path.push(Dir::Body);
+            let body = normal_step(body, env, bound_vars, log, follow(Dir::Body), path);
+            if body.is_none() {
+                path.pop();
+            }
+            if !shadowed {
+                bound_vars.remove(var);
+            }
+            Some(Term::Abstraction(var.clone(), Box::new(body?)))
+        }
+        Term::Application(e1, e2) if matches!(hint.first(), Some(Dir::Arg)) => {
+            // The function is marked normal, only the argument can contain a redex
+            path.push(Dir::Arg);
+            let Some(arg) = normal_step(e2, env, bound_vars, log, follow(Dir::Arg), path) else {
+                path.pop();
+                return None;
+            };
+            Some(Term::Application(e1.clone(), Box::new(arg)))
         }
         Term::Application(e1, e2) => {
             // Only when application is reduced, lookup env variables and substitute
-            let e1 = if let Term::Variable(v) = e1.borrow() {
-                if !bound_vars.contains(v) {
-                    env_var(v, env)
-                } else {
-                    *e1.clone()
+            let inlined = match e1.borrow() {
+                Term::Variable(v) if !bound_vars.contains(v) && is_defined(v, env) => {
+                    log.push(|| Explanation::Delta(v.clone()));
+                    Some(env_var(v, env))
                 }
-            } else {
-                *e1.clone()
+                _ => None,
             };
-            if let Term::Abstraction(var, body) = e1.borrow() {
-                substitute(body, var, e2)
-            } else {
-                Term::Application(
-                    Box::new(beta_reduce(&e1, env, bound_vars.clone())),
-                    Box::new(beta_reduce(e2, env, bound_vars)),
-                )
+            let head = inlined.as_ref().unwrap_or(e1);
+            if let Term::Abstraction(var, body) = head {
+                return Some(contract(var, body, e2, log));
+            }
+            // Only descend into the argument once the function has no redex left
+            path.push(Dir::Fun);
+            if let Some(head) = normal_step(head, env, bound_vars, log, follow(Dir::Fun), path) {
+                return Some(Term::Application(Box::new(head), e2.clone()));
+            }
+            path.pop();
+            path.push(Dir::Arg);
+            if let Some(arg) = normal_step(e2, env, bound_vars, log, &[], path) {
+                return Some(Term::Application(Box::new(head.clone()), Box::new(arg)));
+            }
+            path.pop();
+            inlined.map(|head| Term::Application(Box::new(head), e2.clone()))
+        }
+    }
+}
+
+/// Perform a single leftmost-outermost β-reduction step on a lambda calculus term
+/// The term is returned unchanged if it contains no redex.
+pub fn beta_reduce(term: &Term, env: &Env, bound_vars: HashSet<String>) -> Term {
+    let mut bound_vars = bound_vars;
+    normal_step(
+        term,
+        env,
+        &mut bound_vars,
+        &mut Log(None),
+        &[],
+        &mut Vec::new(),
+    )
+    .unwrap_or_else(|| term.clone())
+}
+
+/// Perform η-reduction on all η-redexes in a term
+/// `λx.(f x)` becomes `f` when `x` is not free in `f`.
+///
+/// See https://en.wikipedia.org/wiki/Lambda_calculus#%CE%B7-reduction.
+pub fn eta_reduce(term: &Term) -> Term {
+    match term {
+        Term::Variable(_) => term.clone(),
+        Term::Abstraction(param, body) => {
+            let body = eta_reduce(body);
+            match &body {
+                // λx.(f x) = f  (x not in free_vars(f))
+                Term::Application(f, x)
+                    if matches!(x.borrow(), Term::Variable(v) if v == param)
+                        && !free_vars(f).contains(param) =>
+                {
+                    *f.clone()
+                }
+                _ => Term::Abstraction(param.clone(), Box::new(body)),
             }
         }
+        Term::Application(e1, e2) => {
+            Term::Application(Box::new(eta_reduce(e1)), Box::new(eta_reduce(e2)))
+        }
+    }
+}
+
+/// Perform η-expansion on a term, the dual of `eta_reduce`
+/// `f` becomes `λx.(f x)` for a fresh `x` not free in `f`.
+/// Abstractions are already in η-long form and are returned unchanged.
+pub fn eta_expand(term: &Term) -> Term {
+    match term {
+        Term::Abstraction(_, _) => term.clone(),
+        _ => {
+            let param = fresh_var("x", &free_vars(term));
+            Term::Abstraction(
+                param.clone(),
+                Box::new(Term::Application(
+                    Box::new(term.clone()),
+                    Box::new(Term::Variable(param)),
+                )),
+            )
+        }
+    }
+}
+
+/// Contract a single redex in a term chosen by a weak or applicative strategy
+/// Env variables in function position are unfolded when nothing else can be reduced.
+/// Returns `None` if the strategy finds no redex to contract.
+fn strategy_step(
+    term: &Term,
+    env: &Env,
+    strategy: Strategy,
+    bound_vars: &HashSet<String>,
+    log: &mut Log,
+) -> Option<Term> {
+    let Term::Application(e1, e2) = term else {
+        // Only applicative order reduces under abstractions
+        let Term::Abstraction(var, body) = term else {
+            return None;
+        };
+        if strategy != Strategy::Applicative {
+            return None;
+        }
+        let mut bound_vars = bound_vars.clone();
+        bound_vars.insert(var.clone());
+        let body = strategy_step(body, env, strategy, &bound_vars, log)?;
+        return Some(Term::Abstraction(var.clone(), Box::new(body)));
+    };
+    let app = |e1: Term, e2: Term| Term::Application(Box::new(e1), Box::new(e2));
+    // Call-by-name contracts the head redex before looking inside it
+    if strategy == Strategy::Cbn {
+        if let Term::Abstraction(var, body) = e1.borrow() {
+            return Some(contract(var, body, e2, log));
+        }
+    }
+    if let Some(e1) = strategy_step(e1, env, strategy, bound_vars, log) {
+        return Some(app(e1, *e2.clone()));
+    }
+    // Arguments are reduced before they are substituted
+    if strategy != Strategy::Cbn {
+        if let Some(e2) = strategy_step(e2, env, strategy, bound_vars, log) {
+            return Some(app(*e1.clone(), e2));
+        }
+    }
+    match e1.borrow() {
+        Term::Abstraction(var, body) => Some(contract(var, body, e2, log)),
+        Term::Variable(v) if !bound_vars.contains(v) && is_defined(v, env) => {
+            log.push(|| Explanation::Delta(v.clone()));
+            Some(app(env_var(v, env), *e2.clone()))
+        }
+        _ => None,
+    }
+}
+
+/// Perform a single reduction step on a term using the given strategy
+/// β-reduces the term, or inlines env variables if no β-reduction applies.
+/// Returns `None` if the term is already in normal form.
+pub fn reduce_step(term: &Term, env: &Env, strategy: Strategy) -> Option<Term> {
+    let mut reduction = Reduction::new(term, env, strategy);
+    reduction.step(&mut Log(None)).then_some(reduction.term)
+}
+
+/// Successive reduction steps of a term
+struct Reduction<'a> {
+    term: Term,
+    env: &'a Env,
+    strategy: Strategy,
+    /// Path to the last contracted redex in normal order, see `normal_step`
+    last_redex: Vec<Dir>,
+    /// Rewrite rules tried before every β-reduction step
+    rules: &'a [RewriteRule],
+}
+
+impl<'a> Reduction<'a> {
+    fn new(term: &Term, env: &'a Env, strategy: Strategy) -> Self {
+        Reduction {
+            term: term.clone(),
+            env,
+            strategy,
+            last_redex: Vec::new(),
+            rules: &[],
+        }
+    }
+
+    /// Perform a single reduction step, recording the rules applied in `log`
+    /// Returns `false` if the term is already in normal form.
+    fn step(&mut self, log: &mut Log) -> bool {
+        if !self.rules.is_empty() {
+            if let Some(next) = rewrite_step(&self.term, self.rules) {
+                log.push(|| Explanation::Rewrite);
+                self.term = next;
+                // The rewritten subterm may lie anywhere, so no part of the term is known normal
+                self.last_redex.clear();
+                return true;
+            }
+        }
+        let (term, env) = (&self.term, self.env);
+        let next = match self.strategy {
+            Strategy::Normal => {
+                let mut path = Vec::new();
+                let mut bound_vars = HashSet::new();
+                let next =
+                    normal_step(term, env, &mut bound_vars, log, &self.last_redex, &mut path);
+                self.last_redex = path;
+                next
+            }
+            _ => strategy_step(term, env, self.strategy, &HashSet::new(), log),
+        };
+        if let Some(next) = next {
+            self.term = next;
+            return true;
+        }
+        // Try to inline variables in the term, which changed it iff a δ-rule fired
+        // This avoids comparing the whole term with its previous version every step
+        let (next, rules) = inline_vars_explained(term, env);
+        if rules.is_empty() {
+            return false;
+        }
+        if let Some(log) = &mut log.0 {
+            log.extend(rules);
+        }
+        self.term = next;
+        true
+    }
+}
+
+/// Perform a single reduction step like `reduce_step`,
+/// also returning the rules that were applied in the order they fired.
+pub fn reduce_step_explained(
+    term: &Term,
+    env: &Env,
+    strategy: Strategy,
+) -> Option<(Term, Vec<Explanation>)> {
+    let mut log = Log(Some(Vec::new()));
+    let mut reduction = Reduction::new(term, env, strategy);
+    reduction
+        .step(&mut log)
+        .then(|| (reduction.term, log.0.unwrap_or_default()))
+}
+
+/// Reduction rules that a normal form has no redexes of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Conversion {
+    /// β-reduction only
+    #[default]
+    Beta,
+    /// β-reduction and η-reduction, so `λx.(f x)` is reducible
+    BetaEta,
+}
+
+/// Check if a term contains no β-redex, nor an η-redex under `Conversion::BetaEta`
+/// Env names are not unfolded, so a normal form may still refer to definitions.
+pub fn is_normal_form(term: &Term, conversion: Conversion) -> bool {
+    match term {
+        Term::Variable(_) => true,
+        // λx.(f x)  (x not in free_vars(f))
+        Term::Abstraction(param, body)
+            if conversion == Conversion::BetaEta
+                && matches!(body.borrow(), Term::Application(f, x)
+                    if matches!(x.borrow(), Term::Variable(v) if v == param)
+                        && !free_vars(f).contains(param)) =>
+        {
+            false
+        }
+        Term::Abstraction(_, body) => is_normal_form(body, conversion),
+        Term::Application(f, x) => {
+            !matches!(f.borrow(), Term::Abstraction(_, _))
+                && is_normal_form(f, conversion)
+                && is_normal_form(x, conversion)
+        }
     }
 }
 
 /// Reduce a term to normal form by repeatedly applying β-reduction
-pub fn reduce_to_normal_form(term: &Term, env: &Env, verbose: bool, printer: PrinterFn) -> Term {
-    let mut term = term.clone();
+/// With `--repl-eval-timeout` or `--max-term-size` the reduction stops early
+/// and the partial term is returned.
+pub fn reduce_to_normal_form(term: &Term, env: &Env, config: &Config, printer: PrinterFn) -> Term {
+    normalize(term, env, config, printer).0
+}
+
+/// Reduce a term to normal form, returning the number of steps taken and
+/// `Error::Timeout`, `Error::TermTooLarge` or `Error::StepLimit` if a limit in `config` was exceeded.
+fn normalize(
+    term: &Term,
+    env: &Env,
+    config: &Config,
+    printer: PrinterFn,
+) -> (Term, usize, Option<Error>) {
+    let deadline = config
+        .repl_eval_timeout
+        .map(|millis| (millis, Instant::now() + Duration::from_millis(millis)));
+    let mut reduction = Reduction::new(term, env, config.strategy);
+    reduction.rules = &config.rules;
+    let mut log = Log(config.explain.then(Vec::new));
+    let mut steps = 0;
     loop {
-        let mut next = beta_reduce(&term, env, HashSet::new());
-        if next == term {
-            // Try to inline variables in the term
-            next = inline_vars(&next, env);
-            if next == term {
-                return term;
+        // At the limit, a term that still takes a step is returned as it was before it
+        let limited = (config.step_limit == Some(steps)).then(|| reduction.term.clone());
+        if !reduction.step(&mut log) {
+            break;
+        }
+        if let Some(term) = limited {
+            return (term, steps, Some(Error::StepLimit { steps }));
+        }
+        steps += 1;
+        for rule in log.0.iter_mut().flat_map(std::mem::take) {
+            printer(rule.to_string());
+        }
+        if config.verbose || config.explain {
+            printer(print::term(&reduction.term));
+        }
+        if let Some((millis, deadline)) = deadline {
+            if Instant::now() >= deadline {
+                return (reduction.term, steps, Some(Error::Timeout { millis }));
             }
         }
-        term = next;
-        if verbose {
-            printer(print::term(&term));
+        if let Some(limit) = config.max_term_size {
+            if term_size(&reduction.term) > limit {
+                return (reduction.term, steps, Some(Error::TermTooLarge { limit }));
+            }
+        }
+    }
+    (reduction.term, steps, None)
+}
+
+/// Reduce a term to normal form within at most `limit` reduction steps
+/// Fails with `Error::StepLimit` if the term is not normalized within the budget.
+pub fn reduce_bounded(
+    term: &Term,
+    env: &Env,
+    strategy: Strategy,
+    limit: usize,
+) -> Result<Term, Error> {
+    reduce_counted(term, env, strategy, limit).map(|(term, _)| term)
+}
+
+/// Reduce a term like `reduce_bounded`, also returning the number of steps taken
+fn reduce_counted(
+    term: &Term,
+    env: &Env,
+    strategy: Strategy,
+    limit: usize,
+) -> Result<(Term, usize), Error> {
+    let mut reduction = Reduction::new(term, env, strategy);
+    for steps in 0..limit {
+        if !reduction.step(&mut Log(None)) {
+            return Ok((reduction.term, steps));
+        }
+    }
+    let term = reduction.term.clone();
+    match reduction.step(&mut Log(None)) {
+        false => Ok((term, limit)),
+        true => Err(Error::StepLimit { steps: limit }),
+    }
+}
+
+/// Normalize a term with normal order reduction and no definitions
+/// Returns `None` if no normal form is reached within `limit` steps, without printing anything.
+pub fn normal_form(term: &Term, limit: usize) -> Option<Term> {
+    reduce_bounded(term, &Env::new(), Strategy::Normal, limit).ok()
+}
+
+/// Reduce a term with normal order reduction and no definitions, collecting every step
+/// Returns the input followed by each intermediate term, up to the normal form or
+/// until `limit` steps were taken, and whether the normal form was reached.
+pub fn trace(term: &Term, limit: usize) -> (Vec<Term>, bool) {
+    let env = Env::new();
+    let mut reduction = Reduction::new(term, &env, Strategy::Normal);
+    let mut terms = vec![term.clone()];
+    for _ in 0..limit {
+        if !reduction.step(&mut Log(None)) {
+            return (terms, true);
         }
+        terms.push(reduction.term.clone());
     }
+    let terminated = !reduction.step(&mut Log(None));
+    (terms, terminated)
+}
+
+/// Reduce a term with normal order reduction like `trace`, unfolding the definitions in `env`
+/// Each term is paired with the path to the redex its step contracts, which is `None` for
+/// the last term and for steps that only unfold definitions.
+pub fn trace_redexes(
+    term: &Term,
+    env: &Env,
+    limit: usize,
+) -> (Vec<(Term, Option<RedexPath>)>, bool) {
+    let mut steps = Vec::new();
+    let mut term = term.clone();
+    for _ in 0..limit {
+        let mut path = Vec::new();
+        let mut bound_vars = HashSet::new();
+        let (next, redex) =
+            match normal_step(&term, env, &mut bound_vars, &mut Log(None), &[], &mut path) {
+                Some(next) => (next, Some(path)),
+                None => {
+                    let (next, rules) = inline_vars_explained(&term, env);
+                    if rules.is_empty() {
+                        steps.push((term, None));
+                        return (steps, true);
+                    }
+                    (next, None)
+                }
+            };
+        steps.push((std::mem::replace(&mut term, next), redex));
+    }
+    let terminated = !Reduction::new(&term, env, Strategy::Normal).step(&mut Log(None));
+    steps.push((term, None));
+    (steps, terminated)
+}
+
+/// Normalize every distinct proper subterm of a term that is not in normal form
+/// Subterms are listed in pre-order with their normal form, or the error if they do not
+/// normalize within `limit` steps. A subterm that occurs several times is only normalized
+/// once. Each subterm is normalized on its own, so variables bound outside of it are free,
+/// and definitions shadowed by an enclosing abstraction are not unfolded.
+pub fn subterm_normal_forms(
+    term: &Term,
+    env: &Env,
+    strategy: Strategy,
+    limit: usize,
+) -> Vec<(Term, Result<Term, Error>)> {
+    let mut seen = HashSet::new();
+    let mut normal_forms = Vec::new();
+    // Subterms with the defined names bound by the abstractions around them, in pre-order
+    let mut stack = vec![(term, Vec::<String>::new())];
+    while let Some((sub, shadowed)) = stack.pop() {
+        match sub {
+            Term::Variable(_) => continue,
+            Term::Abstraction(param, body) => {
+                let mut shadowed = shadowed.clone();
+                if env.contains_key(param) && !shadowed.contains(param) {
+                    shadowed.push(param.clone());
+                    shadowed.sort();
+                }
+                stack.push((body, shadowed));
+            }
+            Term::Application(f, x) => {
+                stack.push((x, shadowed.clone()));
+                stack.push((f, shadowed.clone()));
+            }
+        }
+        if std::ptr::eq(sub, term) || !seen.insert((sub.clone(), shadowed.clone())) {
+            continue;
+        }
+        let unshadowed;
+        let env = match shadowed.is_empty() {
+            true => env,
+            false => {
+                unshadowed = env
+                    .iter()
+                    .filter(|(name, _)| !shadowed.contains(name))
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect::<Env>();
+                &unshadowed
+            }
+        };
+        let normal = reduce_bounded(&inline_vars(sub, env), env, strategy, limit);
+        if normal.as_ref().ok() != Some(sub) {
+            normal_forms.push((sub.clone(), normal));
+        }
+    }
+    normal_forms
+}
+
+/// Paths to every β-redex in a term, including those under abstractions
+/// Redexes are listed in pre-order, so outer redexes come before the redexes inside them
+/// and redexes to the left before those to the right.
+pub fn redexes(term: &Term) -> Vec<RedexPath> {
+    fn collect(term: &Term, path: &mut RedexPath, paths: &mut Vec<RedexPath>) {
+        match term {
+            Term::Variable(_) => {}
+            Term::Abstraction(_, body) => {
+                path.push(Dir::Body);
+                collect(body, path, paths);
+                path.pop();
+            }
+            Term::Application(f, x) => {
+                if matches!(f.as_ref(), Term::Abstraction(_, _)) {
+                    paths.push(path.clone());
+                }
+                path.push(Dir::Fun);
+                collect(f, path, paths);
+                path.pop();
+                path.push(Dir::Arg);
+                collect(x, path, paths);
+                path.pop();
+            }
+        }
+    }
+    let mut paths = Vec::new();
+    collect(term, &mut Vec::new(), &mut paths);
+    paths
+}
+
+/// Contract the β-redex at `path`, or `None` if there is no redex at that position
+fn contract_path(term: &Term, path: &[Dir]) -> Option<Term> {
+    Some(match (term, path) {
+        (Term::Application(f, x), []) => match f.as_ref() {
+            Term::Abstraction(param, body) => contract(param, body, x, &mut Log(None)),
+            _ => return None,
+        },
+        (Term::Abstraction(param, body), [Dir::Body, rest @ ..]) => {
+            Term::Abstraction(param.clone(), Box::new(contract_path(body, rest)?))
+        }
+        (Term::Application(f, x), [Dir::Fun, rest @ ..]) => {
+            Term::Application(Box::new(contract_path(f, rest)?), x.clone())
+        }
+        (Term::Application(f, x), [Dir::Arg, rest @ ..]) => {
+            Term::Application(f.clone(), Box::new(contract_path(x, rest)?))
+        }
+        _ => return None,
+    })
+}
+
+/// Contract the β-redex at `path`, as listed by `redexes`
+/// Fails with `Error::NotARedex` if there is no redex at that position.
+pub fn contract_at(term: &Term, path: &[Dir]) -> Result<Term, Error> {
+    contract_path(term, path).ok_or_else(|| Error::NotARedex(path.to_vec()))
+}
+
+/// Select the leftmost-outermost redex, giving normal order reduction with `reduce_with`
+pub fn normal_order(redexes: &[RedexPath]) -> Option<RedexPath> {
+    redexes.first().cloned()
+}
+
+/// Select the leftmost-innermost redex, giving applicative order reduction with `reduce_with`
+pub fn applicative_order(redexes: &[RedexPath]) -> Option<RedexPath> {
+    // In pre-order, a redex containing others is directly followed by the first of them
+    redexes
+        .iter()
+        .enumerate()
+        .find(|(i, path)| {
+            redexes
+                .get(i + 1)
+                .is_none_or(|next| !next.starts_with(path))
+        })
+        .map(|(_, path)| path.clone())
+}
+
+/// Reduce a closed term by repeatedly contracting the redex chosen by `select`
+/// The selector is given the paths to all β-redexes, as listed by a pre-order traversal.
+/// Reduction stops when no redex remains or the selector returns `None`.
+/// Like normal order reduction of a diverging term, this may not terminate.
+pub fn reduce_with(term: &Term, select: &dyn Fn(&[RedexPath]) -> Option<RedexPath>) -> Term {
+    let mut term = term.clone();
+    loop {
+        let redexes = redexes(&term);
+        if redexes.is_empty() {
+            return term;
+        }
+        let Some(path) = select(&redexes) else {
+            return term;
+        };
+        match contract_path(&term, &path) {
+            Some(next) => term = next,
+            None => return term,
+        }
+    }
+}
+
+/// Normalize a term by normal order β-reduction alone, treating all free names as constants
+/// Nothing is unfolded, not even numeral literals, and no warnings are collected.
+/// Like any normal order reduction, this does not terminate if the term has no normal form.
+pub fn eval_open(term: &Term) -> Term {
+    reduce_with(term, &normal_order)
+}
+
+/// Reduce a term to head normal form `λx1...λxn.(v M1 ... Mm)` by β-reduction alone
+/// Unlike weak head normal form, the head is exposed even under leading abstractions,
+/// but unlike full normal form, the arguments `Mi` are left unreduced.
+/// Like `eval_open`, free names are constants and the reduction may not terminate.
+pub fn reduce_head(term: &Term) -> Term {
+    // In pre-order, a head redex is always the first redex, and only reached through
+    // abstraction bodies and function positions
+    reduce_with(term, &|redexes: &[RedexPath]| {
+        redexes
+            .first()
+            .filter(|path| !path.contains(&Dir::Arg))
+            .cloned()
+    })
+}
+
+/// Perform at most `n` normal order reduction steps on a term
+/// Stops early if the term reaches normal form, returning the intermediate term.
+pub fn eval_steps(term: &Term, n: usize, env: &Env) -> Term {
+    let mut reduction = Reduction::new(term, env, Strategy::Normal);
+    for _ in 0..n {
+        if !reduction.step(&mut Log(None)) {
+            break;
+        }
+    }
+    reduction.term
+}
+
+/// Check if a variable is defined in env or is a numeral literal
+pub fn is_defined(var: &str, env: &Env) -> bool {
+    env.contains_key(var) || numeral_literal(var).is_some()
+}
+
+/// Numeral literals larger than `NUMERAL_CAP` in a term or the definitions it uses
+/// Literals are only counted where they are not defined in env, in ascending order.
+fn large_numerals(term: &Term, env: &Env) -> BTreeSet<usize> {
+    let mut seen = HashSet::new();
+    let mut numerals = BTreeSet::new();
+    let mut stack = vec![term];
+    while let Some(term) = stack.pop() {
+        for var in free_vars(term) {
+            if !seen.insert(var.clone()) {
+                continue;
+            }
+            match (env.get(&var), numeral_literal(&var)) {
+                (Some(value), _) => stack.push(value),
+                (None, Some(n)) if n > NUMERAL_CAP => {
+                    numerals.insert(n);
+                }
+                _ => {}
+            }
+        }
+    }
+    numerals
 }
 
 /// Inline a free variable in env into a term
+/// Undefined numeral literals are unfolded into their Church numeral.
 pub fn env_var(var: &str, env: &Env) -> Term {
-    if let Some(expr) = env.get(var) {
-        // If the variable is in the environment, loop until it is not a variable
-        let mut expr = expr.clone();
-        while let Term::Variable(v) = &expr {
-            if let Some(new_expr) = env.get(v) {
-                expr = new_expr.clone();
-            } else {
-                break;
-            }
+    let mut expr = Term::Variable(var.to_string());
+    // If the variable is in the environment, loop until it is not a variable
+    while let Term::Variable(v) = &expr {
+        if let Some(new_expr) = env.get(v) {
+            expr = new_expr.clone();
+        } else if let Some(n) = numeral_literal(v) {
+            return unfold_numeral(n);
+        } else {
+            break;
         }
-        return expr;
     }
-    Term::Variable(var.to_string())
+    expr
 }
 
 /// Inline variables in a term using the given environment
 pub fn inline_vars(term: &Term, env: &Env) -> Term {
-    match &term {
-        Term::Variable(v) => env_var(v, env),
-        Term::Abstraction(param, body) => {
-            Term::Abstraction(param.clone(), Box::new(inline_vars(body, env)))
+    inline_vars_explained(term, env).0
+}
+
+/// Inline variables like `inline_vars`, also returning a δ-rule for every unfolded name
+/// Variables bound by an enclosing abstraction are never inlined.
+pub fn inline_vars_explained(term: &Term, env: &Env) -> (Term, Vec<Explanation>) {
+    fn inline(
+        term: &Term,
+        env: &Env,
+        bound_vars: &mut Vec<String>,
+        log: &mut Vec<Explanation>,
+    ) -> Term {
+        match &term {
+            Term::Variable(v) if bound_vars.contains(v) => term.clone(),
+            Term::Variable(v) => {
+                let value = env_var(v, env);
+                if !matches!(&value, Term::Variable(u) if u == v) {
+                    log.push(Explanation::Delta(v.clone()));
+                }
+                value
+            }
+            Term::Abstraction(param, body) => {
+                bound_vars.push(param.clone());
+                let body = inline(body, env, bound_vars, log);
+                bound_vars.pop();
+                Term::Abstraction(param.clone(), Box::new(body))
+            }
+            Term::Application(f, x) => Term::Application(
+                Box::new(inline(f, env, bound_vars, log)),
+                Box::new(inline(x, env, bound_vars, log)),
+            ),
         }
-        Term::Application(f, x) => {
-            Term::Application(Box::new(inline_vars(f, env)), Box::new(inline_vars(x, env)))
+    }
+    let mut log = Vec::new();
+    let term = inline(term, env, &mut Vec::new(), &mut log);
+    (term, log)
+}
+
+/// Inline the definitions in `hidden` that a term refers to, transitively
+/// Recursive references to a definition that is already being inlined are kept as names.
+fn inline_hidden(term: &Term, hidden: &Env, expanding: &mut Vec<String>) -> Term {
+    let mut names = free_vars(term)
+        .into_iter()
+        .filter(|v| hidden.contains_key(v) && !expanding.contains(v))
+        .collect::<Vec<_>>();
+    names.sort();
+    let mut term = term.clone();
+    for name in names {
+        expanding.push(name.clone());
+        let value = inline_hidden(&hidden[&name], hidden, expanding);
+        expanding.pop();
+        term = substitute(&term, &name, &value);
+    }
+    term
+}
+
+/// Load the definitions of a file into env, or only the given `names`
+/// Selected definitions have their references to the other definitions
+/// of the file inlined, so that those do not leak into env.
+/// `importing` holds the files whose imports are being loaded, to detect cycles.
+fn import(
+    path: &str,
+    names: Option<&[String]>,
+    env: &mut Env,
+    config: &Config,
+    printer: PrinterFn,
+    warnings: &mut Vec<Warning>,
+    importing: &mut Vec<String>,
+) -> Result<(), Error> {
+    let with_ext = format!("{}.lc", path);
+    let (path, source) = match std::fs::read_to_string(path) {
+        Ok(source) => (path, source),
+        Err(e) => match std::fs::read_to_string(&with_ext) {
+            Ok(source) => (with_ext.as_str(), source),
+            Err(_) => return Err(e.into()),
+        },
+    };
+    if let Some(start) = importing.iter().position(|p| p == path) {
+        let mut cycle = importing[start..].to_vec();
+        cycle.push(path.to_string());
+        return Err(Error::ImportCycle(cycle));
+    }
+    importing.push(path.to_string());
+    let mut defs = Env::new();
+    let source = preprocess(&source);
+    check_nesting(&source, config.max_nesting())?;
+    for expr in parse_prog(&source)? {
+        match &expr {
+            // Only definitions are imported, terms in the file are not evaluated
+            Expr::Term(_) | Expr::Assert(_, _) | Expr::Rule(_) => {}
+            Expr::Import(inner, inner_names) => import(
+                inner,
+                inner_names.as_deref(),
+                &mut defs,
+                config,
+                printer,
+                warnings,
+                importing,
+            )?,
+            Expr::Assignment(_, _) => {
+                eval_expr(&expr, &mut defs, config, printer, warnings)?;
+            }
         }
     }
+    importing.pop();
+    if let Some(names) = names {
+        let mut selected = Env::new();
+        for name in names {
+            let value = defs
+                .remove(name)
+                .ok_or_else(|| Error::UnboundVar(name.clone()))?;
+            selected.insert(name.clone(), value);
+        }
+        for value in selected.values_mut() {
+            *value = inline_hidden(value, &defs, &mut Vec::new());
+        }
+        defs = selected;
+    }
+    let mut defs = defs.into_iter().collect::<Vec<_>>();
+    defs.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, value) in defs {
+        eval_expr(
+            &Expr::Assignment(name, value),
+            env,
+            config,
+            printer,
+            warnings,
+        )?;
+    }
+    Ok(())
 }
 
-pub fn eval_expr(expr: &Expr, env: &mut Env, verbose: bool, printer: PrinterFn) -> Term {
+/// Evaluate a single expression, collecting any diagnostics into `warnings`
+/// Fails if an import cannot be loaded or an assertion does not hold.
+pub fn eval_expr(
+    expr: &Expr,
+    env: &mut Env,
+    config: &Config,
+    printer: PrinterFn,
+    warnings: &mut Vec<Warning>,
+) -> Result<Term, Error> {
     match expr {
         Expr::Assignment(name, val) => {
-            if verbose {
+            if config.verbose {
                 printer(print::assign(name, val));
             }
-            // Explicitly DON'T apply beta reduction here!
+            if env.contains_key(name) {
+                warnings.push(Warning::new(
+                    WarningKind::Shadowing,
+                    format!("`{}` is redefined", name),
+                ));
+            }
+            // Explicitly DON'T apply beta reduction here, unless asked to with `--eager-defs`!
             // We want recursive combinators to not be evaluated until they are used
+            let val = match config.eager_defs {
+                true => {
+                    // Values that look divergent are not reduced at all, since their
+                    // reducts can grow without bound before the step limit is reached
+                    let inlined = inline_vars(val, env);
+                    let reduced = match might_diverge(&inlined) {
+                        true => Err("it might not terminate".to_string()),
+                        false => {
+                            reduce_bounded(&inlined, env, config.strategy, config.step_limit())
+                                .map_err(|e| e.to_string())
+                        }
+                    };
+                    reduced.unwrap_or_else(|reason| {
+                        warnings.push(Warning::new(
+                            WarningKind::MightDiverge,
+                            format!("`{}` is stored unreduced, {}", name, reason),
+                        ));
+                        val.clone()
+                    })
+                }
+                false => val.clone(),
+            };
             env.insert(name.clone(), val.clone());
-            val.clone()
+            Ok(val)
         }
-        Expr::Term(term) => {
-            let term = inline_vars(term, env);
-            if verbose {
-                printer(print::term(&term));
+        Expr::Import(path, names) => {
+            let names = names.as_deref();
+            import(path, names, env, config, printer, warnings, &mut Vec::new())?;
+            // An import has no value of its own
+            Ok(Term::Variable(path.clone()))
+        }
+        Expr::Assert(lhs, rhs) => {
+            check_assertion(lhs, rhs, env, config.step_limit())?;
+            // Neither does an assertion
+            Ok(lhs.clone())
+        }
+        // Rules are only collected by `run_prog`, for the terms after them
+        Expr::Rule(_) => Ok(Term::Variable("rule".to_string())),
+        Expr::Term(term) => Ok(eval_term(term, env, config, printer, warnings).0),
+    }
+}
+
+/// Check that two terms are equal after unfolding definitions and normalizing
+/// Both sides must normalize within `limit` steps to be equal.
+pub fn check_assertion(lhs: &Term, rhs: &Term, env: &Env, limit: usize) -> Result<(), Error> {
+    match definitional_eq(lhs, rhs, env, limit) {
+        true => Ok(()),
+        false => Err(Error::AssertionFailed(lhs.clone(), rhs.clone())),
+    }
+}
+
+/// Reducer with the signature of `reduce_bounded`, that `verify_with` checks
+pub type ReduceFn = fn(&Term, &Env, Strategy, usize) -> Result<Term, Error>;
+
+/// Reduce a term with both normal and applicative order using `reduce_bounded`,
+/// warning if both reach a normal form within `limit` steps and they differ
+pub fn verify(term: &Term, env: &Env, limit: usize) -> Option<Warning> {
+    verify_with(term, env, limit, reduce_bounded)
+}
+
+/// Compare the normal forms that `reduce` finds with normal and applicative order
+/// Terms that do not normalize under one of the strategies are not checked,
+/// since applicative order may diverge where normal order terminates.
+pub fn verify_with(term: &Term, env: &Env, limit: usize, reduce: ReduceFn) -> Option<Warning> {
+    let normal = reduce(term, env, Strategy::Normal, limit).ok()?;
+    let applicative = reduce(term, env, Strategy::Applicative, limit).ok()?;
+    (!alpha_eq(&normal, &applicative)).then(|| {
+        Warning::new(
+            WarningKind::StrategyMismatch,
+            format!(
+                "normal order reduces to `{}` but applicative order to `{}`",
+                print::term_plain(&normal),
+                print::term_plain(&applicative)
+            ),
+        )
+    })
+}
+
+/// Evaluate a term to normal form, returning it with the number of reduction steps taken
+/// and the error that interrupted the reduction, which is also reported as a warning.
+pub(crate) fn eval_term(
+    term: &Term,
+    env: &Env,
+    config: &Config,
+    printer: PrinterFn,
+    warnings: &mut Vec<Warning>,
+) -> (Term, usize, Option<Error>) {
+    // A normal term without env references is its own result, unless a rule may rewrite it
+    let (term, steps, interrupted) = if is_normal_form(term, Conversion::Beta)
+        && !free_vars(term).iter().any(|v| is_defined(v, env))
+        && config.rules.is_empty()
+    {
+        if config.verbose || config.explain {
+            printer(print::term(term));
+        }
+        (term.clone(), 0, None)
+    } else if let Some(&n) = large_numerals(term, env).first() {
+        // Such a literal takes too long to unfold and too much stack to print
+        let e = Error::NumeralCap(n);
+        warnings.push(Warning::new(WarningKind::NumeralCap, e.to_string()));
+        (term.clone(), 0, Some(e))
+    } else {
+        // With rewrite rules, definitions are only unfolded when needed so rules can match them
+        let (term, unfolded) = match config.rules.is_empty() {
+            true => profile::time(Phase::Inlining, || inline_vars_explained(term, env)),
+            false => (term.clone(), Vec::new()),
+        };
+        if config.explain {
+            for rule in unfolded {
+                printer(rule.to_string());
             }
-            reduce_to_normal_form(&term, env, verbose, printer)
         }
+        if might_diverge(&term) {
+            warnings.push(Warning::new(
+                WarningKind::MightDiverge,
+                "term contains a self-application and might not terminate",
+            ));
+        }
+        if config.verbose || config.explain {
+            printer(print::term(&term));
+        }
+        if config.verify {
+            warnings.extend(verify(&term, env, config.step_limit()));
+        }
+        let (term, steps, interrupted) =
+            profile::time(Phase::Reduction, || normalize(&term, env, config, printer));
+        if let Some(e) = &interrupted {
+            let kind = match e {
+                Error::Timeout { .. } => WarningKind::Timeout,
+                Error::StepLimit { .. } => WarningKind::StepLimit,
+                _ => WarningKind::TermTooLarge,
+            };
+            warnings.push(Warning::new(kind, e.to_string()));
+        }
+        (term, steps, interrupted)
+    };
+    // Free variables are left as they are by reduction, so with `--opaque-free-vars`
+    // they are constants rather than mistakes. Defined names are only left in a term
+    // whose reduction was interrupted.
+    let mut unbound = match config.opaque_free_vars {
+        true => Vec::new(),
+        false => free_vars(&term)
+            .into_iter()
+            .filter(|v| !is_defined(v, env))
+            .collect::<Vec<_>>(),
+    };
+    unbound.sort();
+    for var in unbound {
+        warnings.push(Warning::new(
+            WarningKind::UnboundVariable,
+            format!("`{}` is not defined", var),
+        ));
     }
+    (term, steps, interrupted)
 }
 
 /// Run the given input program in the given environment
-pub fn eval_prog(input: String, env: &mut Env, verbose: bool, printer: PrinterFn) {
-    let terms: Program = parse_prog(input.replace("\r", "").trim());
-    for (i, expr) in terms.iter().enumerate() {
-        let term = eval_expr(expr, env, verbose, printer);
-        if matches!(expr, Expr::Assignment(_, _)) {
+/// Returns the warnings collected while evaluating the program.
+pub fn eval_prog(
+    input: String,
+    env: &mut Env,
+    config: &Config,
+    printer: PrinterFn,
+) -> Vec<Warning> {
+    run_prog(input, env, config, printer).warnings
+}
+
+/// Result of running a program with `run_prog`
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// Value of the last statement that has one
+    pub value: Option<Term>,
+    pub warnings: Vec<Warning>,
+    /// Errors of the statements that were skipped, which have already been printed
+    pub errors: Vec<Error>,
+    /// Statistics of the reductions performed, collected with `--stats`
+    pub stats: Option<Stats>,
+    /// Time spent in each phase of running the program, measured with `--profile`
+    pub profile: Option<Profile>,
+}
+
+/// Run a program like `eval_prog`, also returning its value and errors
+///
+/// The value of a program is the value of its last statement that has one.
+/// A term evaluates to its normal form and an assignment to the term it binds,
+/// which is stored in `env` unevaluated. Imports only add definitions to `env`
+/// and have no value, so a program without terms or assignments has value `None`.
+///
+/// A statement that fails to parse or import is reported and skipped,
+/// and the remaining statements are still evaluated.
+/// With `--stats` the substitution statistics of the program are printed last.
+pub fn run_prog(input: String, env: &mut Env, config: &Config, printer: PrinterFn) -> Outcome {
+    if !config.profile {
+        return run_counted(input, env, config, printer);
+    }
+    let (mut outcome, profile) = profile::collect(|| run_counted(input, env, config, printer));
+    eprintln!("Profile: {}", profile);
+    outcome.profile = Some(profile);
+    outcome
+}
+
+/// Run a program, printing the statistics of its reductions with `--stats`
+fn run_counted(input: String, env: &mut Env, config: &Config, printer: PrinterFn) -> Outcome {
+    if !config.stats {
+        return run_statements(input, env, config, printer);
+    }
+    let (mut outcome, stats) = stats::collect(|| run_statements(input, env, config, printer));
+    printer(print::note(&stats.to_string()));
+    outcome.stats = Some(stats);
+    outcome
+}
+
+fn run_statements(input: String, env: &mut Env, config: &Config, printer: PrinterFn) -> Outcome {
+    let mut outcome = Outcome::default();
+    let parsed = profile::time(Phase::Parsing, || {
+        let input = preprocess(&input);
+        check_nesting(&input, config.max_nesting())?;
+        if config.strict_app_arity {
+            check_app_arity(&input)?;
+        }
+        let mut exprs = Vec::new();
+        for statement in parse_statements(&input) {
+            match statement {
+                Ok(prog) => exprs.extend(prog.into_iter().map(Ok)),
+                Err(e) => exprs.push(Err(e)),
+            }
+        }
+        Ok(exprs)
+    });
+    let exprs = match parsed {
+        Ok(exprs) => exprs,
+        Err(e) => {
+            eprintln!("{}", e);
+            outcome.errors.push(e);
+            return outcome;
+        }
+    };
+    let count = exprs.len();
+    let mut config = Cow::Borrowed(config);
+    for (i, expr) in exprs.into_iter().enumerate() {
+        let expr = match expr {
+            Ok(expr) => expr,
+            Err(e) => {
+                eprintln!("{}", e);
+                outcome.errors.push(e);
+                continue;
+            }
+        };
+        if let Expr::Rule(rule) = expr {
+            // A rule applies to the terms after it in the program
+            config.to_mut().rules.push(rule);
+            continue;
+        }
+        let config: &Config = &config;
+        if let Expr::Import(path, names) = &expr {
+            let warnings = &mut outcome.warnings;
+            let imported = import(
+                path,
+                names.as_deref(),
+                env,
+                config,
+                printer,
+                warnings,
+                &mut Vec::new(),
+            );
+            if let Err(e) = imported {
+                eprintln!("Error importing \"{}\": {}", path, e);
+                outcome.errors.push(e);
+            }
+            continue;
+        }
+        if let Expr::Assert(lhs, rhs) = &expr {
+            if let Err(e) = check_assertion(lhs, rhs, env, config.step_limit()) {
+                eprintln!("{}", e);
+                outcome.errors.push(e);
+            }
+            continue;
+        }
+        if let (true, Expr::Term(term)) = (config.subterm_normal_forms, &expr) {
+            for (sub, normal) in
+                subterm_normal_forms(term, env, config.strategy, config.step_limit())
+            {
+                printer(match normal {
+                    Ok(normal) => print::normalized(&sub, &normal),
+                    Err(e) => format!("{} {}", print::term(&sub), print::note(&e.to_string())),
+                });
+            }
+        }
+        if config.show_steps {
+            match eval_with_steps(&expr, env, config, printer, &mut outcome.warnings) {
+                Ok(Some((term, line))) => {
+                    printer(line);
+                    outcome.value = Some(term);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    eprintln!("{}", e);
+                    outcome.errors.push(e);
+                }
+            }
             continue;
         }
-        if verbose {
+        let term = match eval_expr(&expr, env, config, printer, &mut outcome.warnings) {
+            Ok(term) => term,
+            Err(e) => {
+                eprintln!("{}", e);
+                outcome.errors.push(e);
+                continue;
+            }
+        };
+        if let Expr::Assignment(_, _) = expr {
+            outcome.value = Some(term);
+            continue;
+        }
+        if config.verbose {
             // Print all terms and their reduction steps
             // println!("{}", print::term(&term));
-            if i < terms.len() - 1 {
+            if i < count - 1 {
                 print::line(20);
             }
         }
-        if !verbose && i == terms.len() - 1 {
+        if !config.verbose && i == count - 1 {
             // Always print the last term if not in verbose mode
-            printer(print::term(&term));
+            printer(print::result(&term, env, config));
+        }
+        outcome.value = Some(term);
+    }
+    outcome
+}
+
+/// Apply the definition `name` to the terms in `args` from left to right and print the result
+/// Fails if `name` is not defined or an argument does not parse, without evaluating anything.
+pub fn run_entry(
+    name: &str,
+    args: &[String],
+    env: &mut Env,
+    config: &Config,
+    printer: PrinterFn,
+) -> Result<(Term, Vec<Warning>), Error> {
+    if !env.contains_key(name) {
+        return Err(Error::UnboundVar(name.to_string()));
+    }
+    let mut term = Term::Variable(name.to_string());
+    for arg in args {
+        term = Term::Application(Box::new(term), Box::new(parse_term_str(arg)?));
+    }
+    let mut warnings = Vec::new();
+    let (term, ..) = eval_term(&term, env, config, printer, &mut warnings);
+    printer(print::result(&term, env, config));
+    Ok((term, warnings))
+}
+
+/// Evaluate an expression and print its result annotated with the reduction steps taken
+/// Assignments are still stored unevaluated, their value is normalized within
+/// `Config::step_limit` steps only to be printed. Fails like `eval_expr`.
+fn eval_with_steps(
+    expr: &Expr,
+    env: &mut Env,
+    config: &Config,
+    printer: PrinterFn,
+    warnings: &mut Vec<Warning>,
+) -> Result<Option<(Term, String)>, Error> {
+    let steps = |n: usize| print::note(&format!("{} step{}", n, if n == 1 { "" } else { "s" }));
+    match expr {
+        Expr::Term(term) => {
+            let (term, n, _) = eval_term(term, env, config, printer, warnings);
+            let line = format!("{} {}", print::result(&term, env, config), steps(n));
+            Ok(Some((term, line)))
+        }
+        Expr::Assignment(name, val) => {
+            eval_expr(expr, env, config, printer, warnings)?;
+            let inlined = inline_vars(val, env);
+            let line = match reduce_counted(&inlined, env, config.strategy, config.step_limit()) {
+                Ok((term, n)) => format!("{} {}", print::assign(name, &term), steps(n)),
+                Err(e) => format!(
+                    "{} {}",
+                    print::assign(name, val),
+                    print::note(&e.to_string())
+                ),
+            };
+            Ok(Some((val.clone(), line)))
+        }
+        Expr::Import(_, _) | Expr::Assert(_, _) | Expr::Rule(_) => {
+            eval_expr(expr, env, config, printer, warnings)?;
+            Ok(None)
         }
     }
 }
@@ -0,0 +1,17 @@
+use std::path::Path;
+
+use rustyline::history::{FileHistory, History};
+
+/// Load REPL history from a file into the given history
+/// A missing file is treated as an empty history.
+pub fn load_history(history: &mut FileHistory, path: &Path) -> rustyline::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    history.load(path)
+}
+
+/// Save the REPL history to a file so it can be recalled in later sessions
+pub fn save_history(history: &mut FileHistory, path: &Path) -> rustyline::Result<()> {
+    history.save(path)
+}
@@ -0,0 +1,100 @@
+use crate::{
+    analysis::check_definition_order,
+    config::Config,
+    error::Error,
+    eval::{eval_expr, eval_term, Env},
+    parser::{parse_prog, parse_term_str, preprocess, Expr, Term},
+    prelude::prelude_source,
+    warning::Warning,
+    PRINT_NONE,
+};
+
+/// Interpreter for embedding, holding the definitions made so far
+///
+/// Nothing is printed: results are returned and warnings are kept until the next call.
+#[derive(Debug, Clone, Default)]
+pub struct Interpreter {
+    config: Config,
+    env: Env,
+    warnings: Vec<Warning>,
+}
+
+impl Interpreter {
+    /// Create an interpreter without any definitions, not even the prelude
+    pub fn new(config: Config) -> Self {
+        Interpreter {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Evaluate the prelude selected by the configuration, if it is enabled
+    pub fn load_prelude(&mut self) -> Result<(), Error> {
+        let Some(source) = prelude_source(&self.config) else {
+            return Ok(());
+        };
+        let prog = parse_prog(&preprocess(&source?))?;
+        check_definition_order(&prog)?;
+        self.warnings.clear();
+        for expr in &prog {
+            eval_expr(
+                expr,
+                &mut self.env,
+                &self.config,
+                PRINT_NONE,
+                &mut self.warnings,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Bind `name` to the term in `src`, which is stored unreduced unless `--eager-defs` is set
+    pub fn define(&mut self, name: &str, src: &str) -> Result<(), Error> {
+        let term = parse_term_str(&preprocess(src))?;
+        self.warnings.clear();
+        let assignment = Expr::Assignment(name.to_string(), term);
+        eval_expr(
+            &assignment,
+            &mut self.env,
+            &self.config,
+            PRINT_NONE,
+            &mut self.warnings,
+        )?;
+        Ok(())
+    }
+
+    /// Evaluate the term in `src` to normal form using the definitions made so far
+    /// Fails with `Error::StepLimit`, `Error::Timeout` or `Error::TermTooLarge` if a limit
+    /// interrupted the reduction, and with `Error::NumeralCap` if it was not started. Without a step limit in the configuration, reduction stops
+    /// after `DEFAULT_STEP_LIMIT` steps so terms without a normal form do not hang the caller.
+    pub fn eval(&mut self, src: &str) -> Result<Term, Error> {
+        let term = parse_term_str(&preprocess(src))?;
+        self.warnings.clear();
+        let config = Config {
+            step_limit: Some(self.config.step_limit()),
+            ..self.config.clone()
+        };
+        let (value, _, interrupted) =
+            eval_term(&term, &self.env, &config, PRINT_NONE, &mut self.warnings);
+        match interrupted {
+            Some(e) => Err(e),
+            None => Ok(value),
+        }
+    }
+
+    /// Remove all definitions
+    pub fn reset(&mut self) {
+        self.env.clear();
+        self.warnings.clear();
+    }
+
+    /// Definitions made so far
+    pub fn env(&self) -> &Env {
+        &self.env
+    }
+
+    /// Warnings collected by the last call
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use crate::{
+    eval::{free_vars, fresh_var, Env},
+    parser::Term,
+};
+
+/// A suspended computation that is evaluated at most once and then shared
+type Thunk<'a> = Rc<RefCell<ThunkState<'a>>>;
+
+enum ThunkState<'a> {
+    /// Not yet evaluated term in its scope
+    Delayed(&'a Term, Scope<'a>),
+    /// Currently being evaluated, forcing it again means the term loops
+    Forcing,
+    /// Evaluated and shared by all uses
+    Forced(Value<'a>),
+}
+
+/// Weak head normal form values
+#[derive(Clone)]
+enum Value<'a> {
+    /// An abstraction together with the scope it was created in
+    Closure(&'a str, &'a Term, Scope<'a>),
+    /// A free variable applied to (unevaluated) arguments
+    Neutral(String, Vec<Thunk<'a>>),
+}
+
+/// Persistent linked list of bound variables
+#[derive(Clone, Default)]
+struct Scope<'a>(Option<Rc<(&'a str, Thunk<'a>, Scope<'a>)>>);
+
+impl<'a> Scope<'a> {
+    fn bind(&self, name: &'a str, thunk: Thunk<'a>) -> Self {
+        Scope(Some(Rc::new((name, thunk, self.clone()))))
+    }
+
+    fn lookup(&self, name: &str) -> Option<Thunk<'a>> {
+        let mut scope = self;
+        while let Some(node) = &scope.0 {
+            if node.0 == name {
+                return Some(node.1.clone());
+            }
+            scope = &node.2;
+        }
+        None
+    }
+}
+
+fn thunk(state: ThunkState) -> Thunk {
+    Rc::new(RefCell::new(state))
+}
+
+/// Call-by-need evaluation machine
+struct Machine<'a> {
+    env: &'a Env,
+    /// Shared thunks for env definitions, so each is evaluated at most once
+    globals: HashMap<&'a str, Thunk<'a>>,
+    steps: usize,
+    limit: usize,
+}
+
+impl<'a> Machine<'a> {
+    /// Evaluate a term to weak head normal form
+    fn eval(&mut self, term: &'a Term, scope: &Scope<'a>) -> Option<Value<'a>> {
+        match term {
+            Term::Variable(v) => {
+                if let Some(t) = scope.lookup(v) {
+                    return self.force(&t);
+                }
+                let Some((name, value)) = self.env.get_key_value(v) else {
+                    return Some(Value::Neutral(v.clone(), Vec::new()));
+                };
+                let t = self
+                    .globals
+                    .entry(name)
+                    .or_insert_with(|| thunk(ThunkState::Delayed(value, Scope::default())))
+                    .clone();
+                self.force(&t)
+            }
+            Term::Abstraction(param, body) => Some(Value::Closure(param, body, scope.clone())),
+            Term::Application(f, x) => {
+                let f = self.eval(f, scope)?;
+                // The argument is delayed and shared instead of being copied
+                self.apply(f, thunk(ThunkState::Delayed(x, scope.clone())))
+            }
+        }
+    }
+
+    /// Apply a value to a delayed argument
+    fn apply(&mut self, f: Value<'a>, arg: Thunk<'a>) -> Option<Value<'a>> {
+        match f {
+            Value::Closure(param, body, scope) => {
+                self.steps += 1;
+                if self.steps > self.limit {
+                    return None;
+                }
+                self.eval(body, &scope.bind(param, arg))
+            }
+            Value::Neutral(head, mut args) => {
+                args.push(arg);
+                Some(Value::Neutral(head, args))
+            }
+        }
+    }
+
+    /// Evaluate a thunk, or reuse its value if it was already evaluated
+    fn force(&mut self, t: &Thunk<'a>) -> Option<Value<'a>> {
+        let value = match t.replace(ThunkState::Forcing) {
+            ThunkState::Forced(value) => value,
+            ThunkState::Delayed(term, scope) => self.eval(term, &scope)?,
+            ThunkState::Forcing => return None,
+        };
+        *t.borrow_mut() = ThunkState::Forced(value.clone());
+        Some(value)
+    }
+
+    /// Read back a value as a term in normal form
+    /// Binders are given names that are not in `avoid` to prevent variable capture.
+    fn quote(&mut self, value: Value<'a>, avoid: &mut HashSet<String>) -> Option<Term> {
+        match value {
+            Value::Closure(param, body, scope) => {
+                let name = fresh_var(param, avoid);
+                avoid.insert(name.clone());
+                let var = thunk(ThunkState::Forced(Value::Neutral(name.clone(), Vec::new())));
+                let body = self.eval(body, &scope.bind(param, var))?;
+                let body = self.quote(body, avoid);
+                avoid.remove(&name);
+                Some(Term::Abstraction(name, Box::new(body?)))
+            }
+            Value::Neutral(head, args) => {
+                let mut term = Term::Variable(head);
+                for arg in args {
+                    let arg = self.force(&arg)?;
+                    term = Term::Application(Box::new(term), Box::new(self.quote(arg, avoid)?));
+                }
+                Some(term)
+            }
+        }
+    }
+}
+
+/// Reduce a term to normal form using call-by-need (lazy) evaluation
+/// Arguments are evaluated at most once and their results are shared between all uses,
+/// unlike normal order reduction which substitutes and re-reduces copies of them.
+/// Returns the normal form and the number of β-reductions performed,
+/// or `None` if the term does not normalize within `limit` β-reductions.
+pub fn eval_lazy(term: &Term, env: &Env, limit: usize) -> Option<(Term, usize)> {
+    let mut machine = Machine {
+        env,
+        globals: HashMap::new(),
+        steps: 0,
+        limit,
+    };
+    let mut avoid = free_vars(term);
+    for value in env.values() {
+        avoid.extend(free_vars(value));
+    }
+    let value = machine.eval(term, &Scope::default())?;
+    let term = machine.quote(value, &mut avoid)?;
+    Some((term, machine.steps))
+}
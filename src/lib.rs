@@ -0,0 +1,39 @@
+pub mod analysis;
+pub mod binary;
+pub mod cache;
+pub mod completion;
+pub mod config;
+pub mod equiv;
+pub mod error;
+pub mod eval;
+pub mod history;
+pub mod interpreter;
+pub mod lazy;
+pub mod numeral;
+pub mod parser;
+pub mod pattern;
+pub mod prelude;
+pub mod print;
+pub mod profile;
+pub mod ski;
+pub mod stats;
+mod test;
+pub mod token;
+pub mod undo;
+pub mod visit;
+pub mod warning;
+
+use eval::PrinterFn;
+use parser::Term;
+
+pub const PRINT_NONE: PrinterFn = |_| {};
+pub const PRINT_OUT: PrinterFn = |t| println!("{}", t);
+pub const PRINT_DBG: PrinterFn = |t| {
+    println!("{}", t);
+    print::pause("Paused: Enter to step");
+};
+pub const PRINT_OUT_PLAIN: PrinterFn = |t| println!("{}", print::strip_color(&t));
+pub const PRINT_DBG_PLAIN: PrinterFn = |t| {
+    println!("{}", print::strip_color(&t));
+    print::pause("Paused: Enter to step");
+};
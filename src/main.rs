@@ -1,49 +1,129 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::IsTerminal, path::Path};
 
-mod eval;
-mod parser;
-mod print;
-mod test;
+use clap::Parser;
 
-use eval::{eval_prog, PrinterFn};
-use parser::Term;
-
-pub const PRINT_NONE: PrinterFn = |_| {};
-pub const PRINT_OUT: PrinterFn = |t| println!("{}", t);
-pub const PRINT_DBG: PrinterFn = |t| {
-    println!("{}", t);
-    print::pause("Paused: Enter to step");
+use lamda_calc::{
+    analysis::{env_dependencies, env_graph_dot, is_closed},
+    cache::{ResultCache, DEFAULT_CAPACITY},
+    completion::complete,
+    config::{Config, PROJECT_FILE},
+    equiv::{definitional_eq, search},
+    error::Error,
+    eval::{eval_steps, run_entry, run_prog, trace_redexes, Env, Outcome, PrinterFn},
+    history::{load_history, save_history},
+    parser::{debug_ast, grammar_check, parse_prog, preprocess, Expr},
+    prelude::{check_prelude, prelude_config, prelude_source, PRELUDE},
+    print,
+    undo::UndoStack,
+    warning::{reported, Warning},
+    PRINT_DBG, PRINT_DBG_PLAIN, PRINT_NONE, PRINT_OUT, PRINT_OUT_PLAIN,
+};
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::Highlighter,
+    hint::Hinter,
+    history::{FileHistory, History},
+    validate::Validator,
+    Context, Editor, Helper,
 };
 
 fn main() {
     let mut env = HashMap::new();
-    // If one argument is given, read that file, otherwise run REPL
-    let mut args: Vec<String> = std::env::args().collect();
-    // Remove --verbose flag if present
-    let mut verbose = false;
-    args.retain(|x| {
-        match x.as_str() {
-            "--help" | "-h" => help(),
-            "--verbose" | "-v" => verbose = true,
-            _ => return true,
+    // If a program or file is given, evaluate it, otherwise run REPL
+    let config = match std::fs::read_to_string(PROJECT_FILE) {
+        Ok(project) => match Config::project_options(&project) {
+            Ok(options) => Config::try_parse_with_project(&options, std::env::args())
+                .unwrap_or_else(|e| e.exit()),
+            Err(e) => {
+                eprintln!("Error in {}: {}", PROJECT_FILE, e);
+                std::process::exit(2);
+            }
+        },
+        Err(_) => Config::parse(),
+    };
+    let color = !config.canonical && config.color.enabled(std::io::stdout().is_terminal());
+    let (print_out, print_dbg) = if color {
+        (PRINT_OUT, PRINT_DBG)
+    } else {
+        (PRINT_OUT_PLAIN, PRINT_DBG_PLAIN)
+    };
+    if config.ast_only {
+        let source = match read_program(&config) {
+            Some(source) => source,
+            None => {
+                eprintln!("--ast-only requires a file or program");
+                std::process::exit(2);
+            }
+        };
+        match debug_ast(&source) {
+            Ok(ast) => println!("{}", ast),
+            Err(e) => eprintln!("{}", e),
         }
-        false
-    });
-    if args.len() == 2 {
-        eval_prog(
-            std::fs::read_to_string(&args[1]).unwrap(),
-            &mut env,
-            verbose,
-            PRINT_OUT,
-        );
+        return;
+    }
+    if config.grammar_check {
+        let mut failed = false;
+        for (name, result) in grammar_check() {
+            match result {
+                Ok(()) => println!("ok   {}", name),
+                Err(e) => {
+                    println!("FAIL {}\n{}", name, e);
+                    failed = true;
+                }
+            }
+        }
+        std::process::exit(failed as i32);
+    }
+    if let Some(prelude) = prelude_source(&config) {
+        load_prelude(prelude, &mut env, &config, PRINT_NONE);
+    }
+    if config.dump_env_graph {
+        // Only the definitions are of interest, so results are not printed
+        if let Some(program) = read_program(&config) {
+            let outcome = run(program, &mut env, &config, PRINT_NONE);
+            exit_on_errors(&outcome);
+        }
+        println!("{}", env_graph_dot(&env));
+        return;
+    }
+    if let Some(program) = read_program(&config) {
+        let outcome = run(program.clone(), &mut env, &config, print_out);
+        exit_on_errors(&outcome);
+        if let Some(path) = &config.trace_html {
+            trace_html(&program, &env, &config, path);
+        }
+        entry(&mut env, &config, print_out);
+    } else if config.entry.is_some() {
+        entry(&mut env, &config, print_out);
     } else {
-        use std::io::Write;
+        let mut editor = Editor::<ReplHelper, FileHistory>::new().unwrap();
+        editor.set_helper(Some(ReplHelper::default()));
+        let mut undo = UndoStack::new();
+        let mut cache = ResultCache::new(DEFAULT_CAPACITY);
+        if let Some(path) = &config.repl_history {
+            if let Err(e) = load_history(editor.history_mut(), path) {
+                eprintln!("Error loading history: {}", e);
+            }
+        }
         loop {
-            print!("> ");
-            std::io::stdout().flush().unwrap();
-            let mut input = String::new();
-            std::io::stdin().read_line(&mut input).unwrap();
+            if let Some(helper) = editor.helper_mut() {
+                helper.env.clone_from(&env);
+            }
+            let input = match editor.readline("> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+                Err(e) => {
+                    eprintln!("Error reading input: {}", e);
+                    break;
+                }
+            };
+            if !input.trim().is_empty() {
+                let _ = editor.history_mut().add(input.trim());
+            }
             let args: Vec<&str> = input.trim().split(' ').collect::<Vec<&str>>();
+            // Snapshot the environment so definitions can be undone
+            let before = env.clone();
             match *args.first().unwrap_or(&"") {
                 ":q" | ":quit" => break,
                 ":cls" | ":clear" => {
@@ -53,20 +133,36 @@ fn main() {
                 ":env" => {
                     if args.len() == 2 && args[1] == "clear" {
                         env.clear();
+                        undo.record(before, &env);
                     } else {
                         for (name, term) in &env {
-                            println!("{} = {}", name, print::term(term));
+                            print_out(format!("{} = {}", name, print::term(term)));
                         }
                     }
                     continue;
                 }
+                ":undo" => {
+                    if !undo.undo(&mut env) {
+                        eprintln!("Nothing to undo");
+                    }
+                    continue;
+                }
+                ":deps" => {
+                    // Print which definitions each definition references
+                    let mut deps = env_dependencies(&env).into_iter().collect::<Vec<_>>();
+                    deps.sort_by(|a, b| a.0.cmp(&b.0));
+                    for (name, uses) in deps {
+                        let mut uses = uses.into_iter().collect::<Vec<_>>();
+                        uses.sort();
+                        print_out(format!("{}: {}", print::var(&name), uses.join(", ")));
+                    }
+                    continue;
+                }
                 ":std" => {
-                    eval_prog(
-                        include_str!("./std.lc").into(),
-                        &mut env,
-                        verbose,
-                        PRINT_OUT,
-                    );
+                    // The built-in prelude is loaded on request even with `--no-prelude`
+                    let prelude = prelude_source(&config).unwrap_or(Ok(PRELUDE.to_string()));
+                    load_prelude(prelude, &mut env, &config, print_out);
+                    undo.record(before, &env);
                     continue;
                 }
                 ":load" => {
@@ -75,7 +171,8 @@ fn main() {
                         continue;
                     };
                     if let std::io::Result::Ok(content) = std::fs::read_to_string(file) {
-                        eval_prog(content, &mut env, verbose, PRINT_OUT);
+                        run(content, &mut env, &config, print_out);
+                        undo.record(before, &env);
                     } else {
                         eprintln!("Error reading file");
                     }
@@ -84,7 +181,71 @@ fn main() {
                 ":dbg" => {
                     // Step through the program evaluation
                     let input = args[1..].join(" ");
-                    eval_prog(input, &mut env, verbose, PRINT_DBG);
+                    run(input, &mut env, &config, print_dbg);
+                    undo.record(before, &env);
+                    continue;
+                }
+                ":defeq" => {
+                    // Compare two terms after unfolding env definitions
+                    let input = args[1..].join(" ");
+                    match parse_prog(&input).as_deref() {
+                        Ok([Expr::Term(a), Expr::Term(b)]) => {
+                            println!("{}", definitional_eq(a, b, &env, config.step_limit()));
+                        }
+                        Err(e) => eprintln!("{}", e),
+                        _ => eprintln!("Usage: :defeq <term>; <term>;"),
+                    }
+                    continue;
+                }
+                ":closed" => {
+                    // Check for free variables, without unfolding env definitions
+                    let input = args[1..].join(" ");
+                    match parse_prog(&input).as_deref() {
+                        Ok([Expr::Term(term)]) => println!("{}", is_closed(term)),
+                        Err(e) => eprintln!("{}", e),
+                        _ => eprintln!("Usage: :closed <term>;"),
+                    }
+                    continue;
+                }
+                ":search" => {
+                    // Find definitions equal to a term after normalization
+                    let input = args[1..].join(" ");
+                    match parse_prog(&input).as_deref() {
+                        Ok([Expr::Term(term)]) => {
+                            let names = search(term, &env, config.step_limit());
+                            if names.is_empty() {
+                                println!("No matching definitions");
+                            }
+                            for name in names {
+                                print_out(print::var(&name));
+                            }
+                        }
+                        Err(e) => eprintln!("{}", e),
+                        _ => eprintln!("Usage: :search <term>;"),
+                    }
+                    continue;
+                }
+                ":diff" => {
+                    // Highlight the structural differences between two terms
+                    let input = args[1..].join(" ");
+                    match parse_prog(&input).as_deref() {
+                        Ok([Expr::Term(a), Expr::Term(b)]) => print_out(print::term_diff(a, b)),
+                        Err(e) => eprintln!("{}", e),
+                        _ => eprintln!("Usage: :diff <term>; <term>;"),
+                    }
+                    continue;
+                }
+                ":stepn" => {
+                    // Show the term after a fixed number of reduction steps
+                    let steps = args.get(1).and_then(|n| n.parse::<usize>().ok());
+                    let input = args.get(2..).unwrap_or_default().join(" ");
+                    match (steps, parse_prog(&input).as_deref()) {
+                        (Some(n), Ok([Expr::Term(term)])) => {
+                            print_out(print::term(&eval_steps(term, n, &env)));
+                        }
+                        (_, Err(e)) => eprintln!("{}", e),
+                        _ => eprintln!("Usage: :stepn <n> <term>;"),
+                    }
                     continue;
                 }
                 ":help" => {
@@ -93,9 +254,16 @@ fn main() {
                     println!("  :cls, :clear   Clear the screen");
                     println!("  :env           Print the current environment");
                     println!("  :env clear     Clear the current environment");
+                    println!("  :undo          Revert the last change to the environment");
+                    println!("  :deps          Print the dependencies of each definition");
                     println!("  :load <file>   Load a file into the environment");
                     println!("  :std           Load the standard library");
                     println!("  :dbg <prog>    Step through the evaluation");
+                    println!("  :stepn <n> <term> Reduce a term by n steps");
+                    println!("  :defeq <a>; <b>; Compare terms modulo definitions");
+                    println!("  :diff <a>; <b>;  Highlight differences between terms");
+                    println!("  :closed <term>;  Check if a term has no free variables");
+                    println!("  :search <term>;  Find definitions equal to a term");
                     println!("  :help          Print this help message");
                     continue;
                 }
@@ -105,20 +273,139 @@ fn main() {
                 }
                 _ => {}
             }
-            eval_prog(input, &mut env, verbose, PRINT_OUT);
+            // Reuse the result of an expression entered before with the same definitions
+            if let Some((output, warnings)) = cache.get(&input, &env) {
+                print_out(output);
+                report(&warnings, &config);
+                continue;
+            }
+            let outcome = run(input.clone(), &mut env, &config, print_out);
+            // Only results of plain terms are cached, which print their value and
+            // leave the environment unchanged, and only if no steps were printed
+            let pure = parse_prog(&preprocess(&input))
+                .is_ok_and(|prog| prog.iter().all(|expr| matches!(expr, Expr::Term(_))));
+            if pure && !(config.verbose || config.explain || config.show_steps) {
+                if let Some(value) = outcome.value {
+                    let output = print::result(&value, &env, &config);
+                    cache.insert(&input, &env, (output, outcome.warnings));
+                }
+            }
+            undo.record(before, &env);
+        }
+        // Flush the history when leaving the REPL
+        if let Some(path) = &config.repl_history {
+            if let Err(e) = save_history(editor.history_mut(), path) {
+                eprintln!("Error saving history: {}", e);
+            }
         }
     }
 }
 
-fn help() -> ! {
-    println!("Lambda calculus interpreter");
-    println!("Usage: lambda [options] [file]");
-    println!();
-    println!("Options:");
-    println!("  -h, --help     Print this help message");
-    println!("  -v, --verbose  Print debug information");
-    println!("  [file]         File to read lambda calculus program from");
-    println!();
-    println!("If no file is given, the program will run in REPL mode");
-    std::process::exit(0);
+/// Line editor helper completing defined names and keywords with Tab
+#[derive(Default)]
+struct ReplHelper {
+    /// Definitions as of the line being edited
+    env: Env,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(line, pos, &self.env))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/// Run a program and report the warnings it produced
+fn run(input: String, env: &mut Env, config: &Config, printer: PrinterFn) -> Outcome {
+    let outcome = run_prog(input, env, config, printer);
+    report(&outcome.warnings, config);
+    outcome
+}
+
+/// Check and run the prelude read by `prelude_source` with its own configuration
+fn load_prelude(
+    prelude: Result<String, Error>,
+    env: &mut Env,
+    config: &Config,
+    printer: PrinterFn,
+) {
+    match prelude {
+        Ok(prelude) => match check_prelude(&prelude) {
+            Ok(()) => {
+                run(prelude, env, &prelude_config(config), printer);
+            }
+            Err(e) => eprintln!("Error in prelude: {}", e),
+        },
+        Err(e) => eprintln!("Error reading prelude: {}", e),
+    }
+}
+
+/// Evaluate the `--entry` definition applied to its arguments, if one is given
+fn entry(env: &mut Env, config: &Config, printer: PrinterFn) {
+    let Some(name) = &config.entry else {
+        return;
+    };
+    match run_entry(name, &config.args, env, config, printer) {
+        Ok((_, warnings)) => report(&warnings, config),
+        Err(e) => {
+            eprintln!("Error evaluating entry `{}`: {}", name, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write the reduction of the last term of a program to the HTML file at `path`
+fn trace_html(program: &str, env: &Env, config: &Config, path: &Path) {
+    let prog = parse_prog(&preprocess(program)).unwrap_or_default();
+    let Some(term) = prog.iter().rev().find_map(|expr| match expr {
+        Expr::Term(term) => Some(term),
+        _ => None,
+    }) else {
+        eprintln!("--trace-html requires a program ending with a term");
+        std::process::exit(2);
+    };
+    let (steps, terminated) = trace_redexes(term, env, config.step_limit());
+    if let Err(e) = std::fs::write(path, print::trace_html(&steps, terminated)) {
+        eprintln!("Error writing trace: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Read the program given on the command line, exiting if it cannot be read
+fn read_program(config: &Config) -> Option<String> {
+    let source = config.read_program(std::io::stdin())?;
+    Some(source.unwrap_or_else(|e| {
+        eprintln!("Error reading program: {}", e);
+        std::process::exit(1);
+    }))
+}
+
+/// Exit with a failure status if any statement of a program failed
+fn exit_on_errors(outcome: &Outcome) {
+    if !outcome.errors.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Print warnings unless in quiet mode
+fn report(warnings: &[Warning], config: &Config) {
+    for warning in reported(warnings, config) {
+        eprintln!("{}", warning);
+    }
 }
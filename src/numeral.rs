@@ -0,0 +1,118 @@
+use crate::parser::{abs, app, var, Term};
+
+/// Largest numeral literal that a term to evaluate may use
+/// Reducing a numeral to normal form unfolds one layer per step and each step walks the
+/// whole term, so the time grows with the square of the literal and the recursive reducers
+/// need stack for every layer. Terms using a larger literal are not reduced at all.
+pub const NUMERAL_CAP: usize = 1000;
+
+/// Parse a variable name that is a decimal numeral literal like `1000`
+pub fn numeral_literal(name: &str) -> Option<usize> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    name.parse().ok()
+}
+
+/// Unfold one layer of the Church numeral `n`
+/// `0` becomes `λf.λx.x` and `n` becomes `λf.λx.(f ((n-1 f) x))`,
+/// so large numerals are only expanded as far as reduction demands.
+pub fn unfold_numeral(n: usize) -> Term {
+    let f = || Box::new(Term::Variable("f".to_string()));
+    let x = || Box::new(Term::Variable("x".to_string()));
+    let body = match n {
+        0 => *x(),
+        n => {
+            let pred = Box::new(Term::Variable((n - 1).to_string()));
+            let inner = Term::Application(Box::new(Term::Application(pred, f())), x());
+            Term::Application(f(), Box::new(inner))
+        }
+    };
+    Term::Abstraction(
+        "f".to_string(),
+        Box::new(Term::Abstraction("x".to_string(), Box::new(body))),
+    )
+}
+
+/// Build the Church numeral `λf.λx.(f (f ... x))` applying `f` to `x` exactly `n` times
+/// Unlike `unfold_numeral` the numeral is fully expanded and in normal form.
+pub fn to_church_numeral(n: usize) -> Term {
+    let body = (0..n).fold(var("x"), |body, _| app(var("f"), body));
+    abs("f", abs("x", body))
+}
+
+/// Decode a Church numeral `λf.λx.(f (f ... x))` in normal form into a number
+pub fn from_church_numeral(term: &Term) -> Option<usize> {
+    let Term::Abstraction(f, body) = term else {
+        return None;
+    };
+    let Term::Abstraction(x, body) = body.as_ref() else {
+        return None;
+    };
+    let mut body = body.as_ref();
+    if f == x {
+        return None;
+    }
+    let mut n = 0;
+    while let Term::Application(g, inner) = body {
+        if !matches!(g.as_ref(), Term::Variable(g) if g == f) {
+            return None;
+        }
+        n += 1;
+        body = inner;
+    }
+    matches!(body, Term::Variable(v) if v == x).then_some(n)
+}
+
+/// Decode a Church boolean `λt.λf.t` or `λt.λf.f` in normal form
+pub fn from_church_boolean(term: &Term) -> Option<bool> {
+    let Term::Abstraction(t, body) = term else {
+        return None;
+    };
+    let Term::Abstraction(f, body) = body.as_ref() else {
+        return None;
+    };
+    match body.as_ref() {
+        _ if t == f => None,
+        Term::Variable(v) if v == t => Some(true),
+        Term::Variable(v) if v == f => Some(false),
+        _ => None,
+    }
+}
+
+/// Decode a signed integer `λf.((f isNeg) n)` in normal form into a number
+/// Church numerals are decoded as non-negative integers.
+pub fn from_signed_numeral(term: &Term) -> Option<isize> {
+    if let Some(n) = from_church_numeral(term) {
+        return isize::try_from(n).ok();
+    }
+    let Term::Abstraction(f, body) = term else {
+        return None;
+    };
+    let Term::Application(inner, magnitude) = body.as_ref() else {
+        return None;
+    };
+    let Term::Application(g, sign) = inner.as_ref() else {
+        return None;
+    };
+    if !matches!(g.as_ref(), Term::Variable(g) if g == f) {
+        return None;
+    }
+    let negative = from_church_boolean(sign)?;
+    let n = isize::try_from(from_church_numeral(magnitude)?).ok()?;
+    Some(if negative { -n } else { n })
+}
+
+/// Decode a Church numeral, boolean or signed integer in normal form into its literal,
+/// such as `3`, `true` or `-3`
+/// `λa.λb.b` is both `0` and `false`: it is read as `0` if the outer binder is
+/// named `f` like in `λf.λx.x`, and as `false` otherwise like in `λt.λf.f`.
+pub fn decode_literal(term: &Term) -> Option<String> {
+    let numeral_binder = matches!(term, Term::Abstraction(f, _) if f == "f");
+    match (from_church_numeral(term), from_church_boolean(term)) {
+        (Some(n), None) => Some(n.to_string()),
+        (Some(n), Some(_)) if numeral_binder => Some(n.to_string()),
+        (_, Some(b)) => Some(b.to_string()),
+        (None, None) => from_signed_numeral(term).map(|n| n.to_string()),
+    }
+}
@@ -1,6 +1,19 @@
-use pest::{iterators::Pair, Parser};
+use std::collections::{HashMap, HashSet};
+
+use pest::{
+    error::{Error as PestError, ErrorVariant},
+    iterators::Pair,
+    Parser, Position,
+};
 use pest_derive::Parser;
 
+use crate::{
+    error::Error,
+    eval::{free_vars, fresh_var, substitute},
+    numeral::numeral_literal,
+    pattern::RewriteRule,
+};
+
 /// Lambda calculus parser using pest
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
@@ -11,74 +24,628 @@ pub struct LambdaCalcParser;
 pub enum Expr {
     Assignment(String, Term),
     Term(Term),
+    /// Import the definitions of a file, or only the listed names
+    Import(String, Option<Vec<String>>),
+    /// Check that two terms are equal after normalization, `assert a == b`
+    Assert(Term, Term),
+    /// Rewrite subterms matching a pattern during normalization, `rule lhs => rhs`
+    Rule(RewriteRule),
 }
 
+/// Prefix of the parameter of a type abstraction `Λt.body`
+/// Names in the grammar are ASCII, so marked parameters never clash with other names.
+pub const TYPE_MARKER: char = 'Λ';
+
+/// Error produced when input does not match the grammar
+pub type ParseError = Box<PestError<Rule>>;
+
 /// A program is a list of expressions
 pub type Program = Vec<Expr>;
 
 /// AST for lambda calculus
 ///
 /// See https://en.wikipedia.org/wiki/Lambda_calculus#Definition.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Term {
     Variable(String),
     Abstraction(String, Box<Term>),
     Application(Box<Term>, Box<Term>),
 }
 
-/// Parse a top-level program into a list of terms
-pub fn parse_prog(input: &str) -> Program {
-    /// Transform a Pest pair into our own AST Expr node format
-    fn parse_term(pair: Pair<Rule>) -> Term {
-        match pair.as_rule() {
-            Rule::variable => Term::Variable(pair.as_str().to_string()),
-            Rule::abstraction => {
-                let mut inner = pair.into_inner();
-                let param = inner.next().unwrap().as_str().to_string();
-                let body = parse_term(inner.next().unwrap());
+/// Construct a variable term `name`
+pub fn var(name: &str) -> Term {
+    Term::Variable(name.to_string())
+}
+
+/// Construct an abstraction term `λparam.body`
+pub fn abs(param: &str, body: Term) -> Term {
+    Term::Abstraction(param.to_string(), Box::new(body))
+}
+
+/// Construct an application term `(f x)`
+pub fn app(f: Term, x: Term) -> Term {
+    Term::Application(Box::new(f), Box::new(x))
+}
+
+/// Construct an assignment expression `name = value`
+pub fn assign(name: &str, value: Term) -> Expr {
+    Expr::Assignment(name.to_string(), value)
+}
+
+/// Associativity of an infix operator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assoc {
+    Left,
+    Right,
+}
+
+/// Infix operator declared by `infixl 6 + = add;`
+#[derive(Debug, Clone)]
+struct Operator {
+    assoc: Assoc,
+    precedence: u8,
+    /// Function the operator desugars to, `a + b` becomes `add a b`
+    function: String,
+}
+
+/// Macro declared by `macro twice(f) = λx.(f (f x));`
+#[derive(Debug, Clone)]
+struct Macro {
+    params: Vec<String>,
+    body: Term,
+}
+
+/// Syntax extensions declared so far in a program
+#[derive(Debug, Default)]
+struct Syntax {
+    /// Infix operators by their symbol
+    operators: HashMap<String, Operator>,
+    /// Macros by their name
+    macros: HashMap<String, Macro>,
+}
+
+/// Transform an infix declaration pair into its symbol and operator
+fn parse_infix_decl(pair: Pair<Rule>) -> (String, Operator) {
+    let mut inner = pair.into_inner();
+    let assoc = match inner.next().unwrap().as_str() {
+        "infixl" => Assoc::Left,
+        _ => Assoc::Right,
+    };
+    let precedence = inner.next().unwrap().as_str().parse().unwrap();
+    let symbol = inner.next().unwrap().as_str().to_string();
+    let function = inner.next().unwrap().as_str().to_string();
+    let op = Operator {
+        assoc,
+        precedence,
+        function,
+    };
+    (symbol, op)
+}
+
+/// Desugar a chain of operands separated by infix operators into applications
+/// Operators bind looser than juxtaposition, so `f a + b` is `add (f a) b`.
+fn parse_infix(pair: Pair<Rule>, syntax: &Syntax) -> Result<Term, Error> {
+    let mut inner = pair.into_inner();
+    let mut operands = vec![parse_term(inner.next().unwrap(), syntax)?];
+    let mut pending: Vec<&Operator> = Vec::new();
+    // Combine the two topmost operands with the topmost pending operator
+    let reduce = |operands: &mut Vec<Term>, op: &Operator| {
+        let rhs = operands.pop().unwrap();
+        let lhs = operands.pop().unwrap();
+        operands.push(app(app(var(&op.function), lhs), rhs));
+    };
+    while let Some(symbol) = inner.next() {
+        let Some(op) = syntax.operators.get(symbol.as_str()) else {
+            return Err(Error::from(PestError::new_from_span(
+                ErrorVariant::CustomError {
+                    message: format!("undeclared infix operator `{}`", symbol.as_str()),
+                },
+                symbol.as_span(),
+            )));
+        };
+        while let Some(top) = pending.last() {
+            let binds_tighter = top.precedence > op.precedence
+                || (top.precedence == op.precedence && op.assoc == Assoc::Left);
+            if !binds_tighter {
+                break;
+            }
+            reduce(&mut operands, pending.pop().unwrap());
+        }
+        pending.push(op);
+        operands.push(parse_term(inner.next().unwrap(), syntax)?);
+    }
+    while let Some(op) = pending.pop() {
+        reduce(&mut operands, op);
+    }
+    Ok(operands.pop().unwrap())
+}
+
+/// Transform a macro declaration pair into its name and macro
+fn parse_macro_decl(pair: Pair<Rule>, syntax: &Syntax) -> Result<(String, Macro), Error> {
+    let mut inner = pair.into_inner().collect::<Vec<_>>();
+    let body = parse_term(inner.pop().unwrap(), syntax)?;
+    let mut names = inner.into_iter().map(|p| p.as_str().to_string());
+    let name = names.next().unwrap();
+    let params = names.collect();
+    Ok((name, Macro { params, body }))
+}
+
+/// Expand a macro invocation `twice(g)` by substituting the arguments into its body
+/// Invocations of names that are not macros read like applications to a group,
+/// so `f(a)` is `f (a)` and `f(a, b)` is `f (a, b)` applying `f` to a pair.
+fn parse_macro_call(pair: Pair<Rule>, syntax: &Syntax) -> Result<Term, Error> {
+    let span = pair.as_span();
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_str();
+    let args = inner
+        .map(|arg| parse_term(arg, syntax))
+        .collect::<Result<Vec<_>, _>>()?;
+    let error = |message| {
+        Error::from(PestError::new_from_span(
+            ErrorVariant::CustomError { message },
+            span,
+        ))
+    };
+    let Some(m) = syntax.macros.get(name) else {
+        let mut args = args.into_iter();
+        return match (args.next(), args.next(), args.next()) {
+            (Some(a), None, _) => Ok(app(var(name), a)),
+            (Some(a), Some(b), None) => Ok(app(var(name), pair_term(a, b))),
+            _ => Err(error(format!(
+                "`{}` is not a macro and can only be applied to a term or a pair",
+                name
+            ))),
+        };
+    };
+    if args.len() != m.params.len() {
+        return Err(error(format!(
+            "macro `{}` expects {} arguments but got {}",
+            name,
+            m.params.len(),
+            args.len()
+        )));
+    }
+    // Rename the parameters apart first, so that arguments mentioning
+    // another parameter are not substituted again
+    let mut avoid = free_vars(&m.body);
+    avoid.extend(args.iter().flat_map(free_vars));
+    let mut body = m.body.clone();
+    let mut placeholders = Vec::new();
+    for param in &m.params {
+        let placeholder = fresh_var(param, &avoid);
+        avoid.insert(placeholder.clone());
+        body = substitute(&body, param, &var(&placeholder));
+        placeholders.push(placeholder);
+    }
+    for (placeholder, arg) in placeholders.iter().zip(&args) {
+        body = substitute(&body, placeholder, arg);
+    }
+    Ok(body)
+}
+
+/// Build the Church pair `λf.(f a b)`, renaming `f` apart from the components
+fn pair_term(a: Term, b: Term) -> Term {
+    let mut avoid = free_vars(&a);
+    avoid.extend(free_vars(&b));
+    let f = fresh_var("f", &avoid);
+    abs(&f, app(app(var(&f), a), b))
+}
+
+/// Transform a Pest pair into our own AST Expr node format
+fn parse_term(pair: Pair<Rule>, syntax: &Syntax) -> Result<Term, Error> {
+    Ok(match pair.as_rule() {
+        Rule::variable => {
+            let name = pair.as_str();
+            if name.chars().all(|c| c.is_ascii_digit()) && numeral_literal(name).is_none() {
+                return Err(Error::NumeralOverflow);
+            }
+            Term::Variable(name.to_string())
+        }
+        Rule::abstraction => {
+            let mut inner = pair.into_inner().peekable();
+            let is_type = inner
+                .next_if(|p| p.as_rule() == Rule::type_lambda)
+                .is_some();
+            let param = inner.next().unwrap().as_str().to_string();
+            let body = parse_term(inner.next().unwrap(), syntax)?;
+            if is_type {
+                // The parameter is marked in the body too, so the binder is printed as `Λ`
+                let marked = format!("{}{}", TYPE_MARKER, param);
+                let body = substitute(&body, &param, &var(&marked));
+                Term::Abstraction(marked, Box::new(body))
+            } else {
                 Term::Abstraction(param, Box::new(body))
             }
-            // Rule::application => {
-            //     let mut inner = pair.into_inner();
-            //     let lhs = parse_term(inner.next().unwrap());
-            //     let rhs = parse_term(inner.next().unwrap());
-            //     Term::Application(Box::new(lhs), Box::new(rhs))
-            // }
-            // rhs is one or more terms
-            Rule::application => {
-                // Syntax sugar: (e1 e2 e3 ...) -> (e1 (e2 (e3 ...)))
-                // Previous (e1 e2) was only allowed
-                let mut inner = pair.into_inner();
-                let mut lhs = parse_term(inner.next().unwrap());
-                for rhs in inner {
-                    lhs = Term::Application(Box::new(lhs), Box::new(parse_term(rhs)));
-                }
-                lhs
+        }
+        // Rule::application => {
+        //     let mut inner = pair.into_inner();
+        //     let lhs = parse_term(inner.next().unwrap());
+        //     let rhs = parse_term(inner.next().unwrap());
+        //     Term::Application(Box::new(lhs), Box::new(rhs))
+        // }
+        // rhs is one or more terms
+        Rule::application => {
+            // Syntax sugar: (e1 e2 e3 ...) -> (e1 (e2 (e3 ...)))
+            // Previous (e1 e2) was only allowed
+            let mut inner = pair.into_inner();
+            let mut lhs = parse_term(inner.next().unwrap(), syntax)?;
+            for rhs in inner {
+                lhs = Term::Application(Box::new(lhs), Box::new(parse_term(rhs, syntax)?));
             }
-            r => unreachable!("Rule {:?} not expected", r),
+            lhs
+        }
+        Rule::group => {
+            let mut inner = pair.into_inner();
+            let a = parse_term(inner.next().unwrap(), syntax)?;
+            let Some(b) = inner.next() else {
+                return Ok(a);
+            };
+            pair_term(a, parse_term(b, syntax)?)
+        }
+        Rule::block => {
+            // begin a = x; b = y; body end -> ((λa.((λb.body) y)) x)
+            // Like `let`, a definition is not in scope of its own value, only of those after it
+            let mut inner = pair.into_inner().collect::<Vec<_>>();
+            let body = parse_term(inner.pop().unwrap(), syntax)?;
+            let defs = inner
+                .into_iter()
+                .map(|def| parse_assignment(def, syntax))
+                .collect::<Result<Vec<_>, _>>()?;
+            defs.into_iter()
+                .rev()
+                .fold(body, |body, (name, value)| app(abs(&name, body), value))
         }
+        Rule::expr => return parse_infix(pair, syntax),
+        Rule::macro_call => return parse_macro_call(pair, syntax),
+        r => unreachable!("Rule {:?} not expected", r),
+    })
+}
+
+/// Transform an assignment pair into its name and term
+fn parse_assignment(pair: Pair<Rule>, syntax: &Syntax) -> Result<(String, Term), Error> {
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_str().to_string();
+    let term = parse_term(inner.next().unwrap(), syntax)?;
+    Ok((name, term))
+}
+
+/// Transform an import pair into the imported path and selected names
+fn parse_import(pair: Pair<Rule>) -> Expr {
+    let mut inner = pair.into_inner();
+    let path = inner.next().unwrap().as_str().trim_matches('"').to_string();
+    let names = inner
+        .map(|name| name.as_str().to_string())
+        .collect::<Vec<_>>();
+    Expr::Import(path, (!names.is_empty()).then_some(names))
+}
+
+/// Desugar mutually recursive definitions `a = ... and b = ...` into a single fixpoint
+///
+/// The definitions are combined into a tuple `T = Y (λp.λs.(s a' b' ...))`
+/// where each reference to a name in the group is replaced by a projection `(p select_i)`.
+/// Each name is then defined as its projection `(T select_i)` of the tuple.
+fn desugar_mutual(defs: Vec<(String, Term)>) -> Vec<Expr> {
+    let n = defs.len();
+    // select_i = λx0.λx1...λx{n-1}.x_i
+    let select = |i: usize| {
+        (0..n).rev().fold(var(&format!("x{}", i)), |body, j| {
+            abs(&format!("x{}", j), body)
+        })
+    };
+    let mut avoid = defs
+        .iter()
+        .flat_map(|(_, t)| free_vars(t))
+        .collect::<HashSet<_>>();
+    let p = fresh_var("p", &avoid);
+    avoid.insert(p.clone());
+    let s = fresh_var("s", &avoid);
+    let tuple = defs.iter().fold(var(&s), |tuple, (_, body)| {
+        let body = defs
+            .iter()
+            .enumerate()
+            .fold(body.clone(), |body, (i, (name, _))| {
+                substitute(&body, name, &app(var(&p), select(i)))
+            });
+        app(tuple, body)
+    });
+    let x = || app(var("f"), app(var("x"), var("x")));
+    let y = abs("f", app(abs("x", x()), abs("x", x())));
+    let fix = app(y, abs(&p, abs(&s, tuple)));
+    defs.into_iter()
+        .enumerate()
+        .map(|(i, (name, _))| Expr::Assignment(name, app(fix.clone(), select(i))))
+        .collect()
+}
+
+/// Parse a single term, rejecting any trailing input
+pub fn parse_term_str(input: &str) -> Result<Term, Error> {
+    let mut pairs = LambdaCalcParser::parse(Rule::single, input)?;
+    parse_term(pairs.next().unwrap(), &Syntax::default())
+}
+
+impl std::str::FromStr for Term {
+    type Err = Error;
+
+    /// Parse a single term like `parse_term_str`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_term_str(s)
     }
+}
 
-    let mut prog = Program::new();
-    let pairs = match LambdaCalcParser::parse(Rule::program, input) {
-        Ok(pairs) => pairs,
-        Err(e) => {
-            eprintln!("{}", e);
-            return prog;
+/// Check that no application juxtaposes more than two terms without parentheses
+/// Used by `--strict-app-arity` to reject `f a b` in favor of the explicit `((f a) b)`.
+pub fn check_app_arity(input: &str) -> Result<(), Error> {
+    let pairs = LambdaCalcParser::parse(Rule::program, input)?;
+    let mut stack = pairs.collect::<Vec<_>>();
+    while let Some(pair) = stack.pop() {
+        if pair.as_rule() == Rule::application && pair.clone().into_inner().count() > 2 {
+            return Err(Error::from(PestError::new_from_span(
+                ErrorVariant::CustomError {
+                    message: "more than two juxtaposed terms, add parentheses like ((f a) b)"
+                        .to_string(),
+                },
+                pair.as_span(),
+            )));
         }
+        stack.extend(pair.into_inner());
+    }
+    Ok(())
+}
+
+/// Default maximum depth of nested parentheses and abstractions accepted by `check_nesting`
+///
+/// A printed Church numeral `n` nests `n + 2` levels deep, so numerals above 254 can only be
+/// read back with a higher `--max-nesting`. Numeral literals like `300` are names and do not nest.
+pub const DEFAULT_MAX_NESTING: usize = 256;
+
+/// Check that parentheses, blocks and abstractions are nested at most `max` levels deep
+/// Deeper input would overflow the stack of the recursive descent parser,
+/// so it is rejected up front with an error pointing at the first token too deep.
+///
+/// An abstraction stays open until its body ends, which is right after a variable body
+/// or at the parenthesis or `end` closing a group or block body.
+pub fn check_nesting(input: &str, max: usize) -> Result<(), Error> {
+    // Abstractions whose bodies are the open groups, and the abstractions waiting for a body
+    let mut groups: Vec<usize> = Vec::new();
+    let mut pending = 0usize;
+    let mut open = 0usize;
+    let mut param = false;
+    let too_deep = |i: usize| {
+        Error::from(PestError::new_from_pos(
+            ErrorVariant::CustomError {
+                message: format!("nesting too deep, more than {} levels", max),
+            },
+            Position::new(input, i).unwrap(),
+        ))
     };
+    let mut chars = input.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        // Blocks nest like groups, and names are skipped as a whole
+        let c = match c {
+            c if c.is_ascii_alphanumeric() => {
+                let mut end = i + c.len_utf8();
+                while let Some(&(j, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '\'') {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+                match &input[i..end] {
+                    // A parameter does not end the abstraction it belongs to
+                    _ if param => {
+                        param = false;
+                        continue;
+                    }
+                    "begin" => '(',
+                    "end" => ')',
+                    // The arguments of a macro call are the body
+                    _ if input[end..].starts_with('(') => continue,
+                    _ => {
+                        pending = 0;
+                        continue;
+                    }
+                }
+            }
+            c => c,
+        };
+        match c {
+            // Parentheses in comments and strings do not nest
+            '-' if input[i..].starts_with("--") => {
+                chars.by_ref().find(|&(_, c)| c == '\n');
+            }
+            '"' => {
+                chars.by_ref().find(|&(_, c)| c == '"');
+            }
+            'λ' | '\\' | 'Λ' => {
+                pending += 1;
+                param = true;
+                if open + groups.len() + pending > max {
+                    return Err(too_deep(i));
+                }
+            }
+            '(' => {
+                open += pending;
+                groups.push(pending);
+                pending = 0;
+                if open + groups.len() > max {
+                    return Err(too_deep(i));
+                }
+            }
+            ')' => {
+                open -= groups.pop().unwrap_or(0);
+                pending = 0;
+            }
+            ';' => pending = 0,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Normalize source text before parsing
+/// Converts CRLF and lone CR line endings to LF and strips trailing whitespace
+/// from every line, as well as surrounding blank lines.
+pub fn preprocess(input: &str) -> String {
+    input
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Format the raw AST of a program in Rust debug notation, for diagnosing misparses
+pub fn debug_ast(input: &str) -> Result<String, Error> {
+    Ok(format!("{:#?}", parse_prog(&preprocess(input))?))
+}
+
+/// Parse a top-level program into a list of expressions, failing at the first error
+/// Infix operators can be used after their declaration in the same program.
+pub fn parse_prog(input: &str) -> Result<Program, Error> {
+    parse_with_syntax(input, &mut Syntax::default())
+}
+
+/// Representative programs the grammar must accept, each with a short description
+pub const GRAMMAR_SNIPPETS: &[(&str, &str)] = &[
+    ("identity", "λx.x;"),
+    ("ascii lambda", "\\x.x;"),
+    ("arrow lambda", "\\x -> x;"),
+    ("application chain", "f a b c;"),
+    ("explicit application", "f @ a @ b;"),
+    ("nested abstractions", "λf.λx.(f (f x));"),
+    ("nested parentheses", "((((λx.x) y)));"),
+    ("assignment", "I = λx.x;"),
+    ("mutual definitions", "Even = λn.n and Odd = λn.n;"),
+    ("pair", "(a, b);"),
+    ("block", "begin I = λx.x; I a end;"),
+    ("assertion", "assert (λx.x) a == a;"),
+    ("import", "import \"std.lc\" (True, False);"),
+    ("rewrite rule", "rule (Add 0 ?n) => ?n;"),
+    ("infix operator", "infixl 6 + = Add; a + b;"),
+    ("macro", "macro twice(g) = λx.(g (g x)); twice(f);"),
+    ("comments", "-- identity\nλx.x; -- trailing"),
+    ("primed names", "x' = λx''.x'';"),
+];
+
+/// Parse every program in `GRAMMAR_SNIPPETS`, returning each description with the outcome
+pub fn grammar_check() -> Vec<(&'static str, Result<(), Error>)> {
+    GRAMMAR_SNIPPETS
+        .iter()
+        .map(|(name, source)| (*name, parse_prog(source).map(|_| ())))
+        .collect()
+}
+
+/// Parse a program statement by statement, so an invalid statement does not affect the others
+/// Statements end at every `;` outside of comments, strings and `begin … end` blocks.
+/// Operators and macros declared in a statement can be used in the following ones, and error
+/// positions refer to the whole input. Valid programs are parsed at once, as by `parse_prog`.
+pub fn parse_statements(input: &str) -> Vec<Result<Program, Error>> {
+    if let Ok(prog) = parse_prog(input) {
+        return vec![Ok(prog)];
+    }
+    let mut syntax = Syntax::default();
+    let mut results = Vec::new();
+    // Line and column where the next statement starts
+    let (mut line, mut column) = (0, 0);
+    let mut start = 0;
+    // Number of blocks open, whose definitions end with `;` inside the statement
+    let mut blocks = 0usize;
+    let mut chars = input.char_indices().peekable();
+    loop {
+        let end = match chars.next() {
+            Some((i, '-')) if input[i..].starts_with("--") => {
+                chars.by_ref().find(|&(_, c)| c == '\n');
+                continue;
+            }
+            Some((_, '"')) => {
+                chars.by_ref().find(|&(_, c)| c == '"');
+                continue;
+            }
+            Some((i, c)) if c.is_ascii_alphanumeric() => {
+                let mut end = i + 1;
+                while let Some(&(j, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '\'') {
+                        break;
+                    }
+                    end = j + c.len_utf8();
+                    chars.next();
+                }
+                match &input[i..end] {
+                    "begin" => blocks += 1,
+                    "end" => blocks = blocks.saturating_sub(1),
+                    _ => {}
+                }
+                continue;
+            }
+            Some((i, ';')) if blocks == 0 => i + 1,
+            Some(_) => continue,
+            None => input.len(),
+        };
+        let statement = &input[start..end];
+        if !statement.trim().is_empty() {
+            // Blank out the preceding input to keep the statement at its position
+            let padded = "\n".repeat(line) + &" ".repeat(column) + statement;
+            results.push(parse_with_syntax(&padded, &mut syntax));
+        }
+        for c in statement.chars() {
+            match c {
+                '\n' => (line, column) = (line + 1, 0),
+                _ => column += 1,
+            }
+        }
+        start = end;
+        if end == input.len() {
+            return results;
+        }
+    }
+}
+
+/// Parse a program with the operators and macros declared so far, adding its declarations
+fn parse_with_syntax(input: &str, syntax: &mut Syntax) -> Result<Program, Error> {
+    let mut prog = Program::new();
+    let pairs = LambdaCalcParser::parse(Rule::program, input)?;
     for pair in pairs {
         match pair.as_rule() {
             Rule::EOI => break,
-            Rule::assignment => {
+            Rule::import => prog.push(parse_import(pair)),
+            Rule::assertion => {
                 let mut inner = pair.into_inner();
-                let name = inner.next().unwrap().as_str().to_string();
-                let term = parse_term(inner.next().unwrap());
+                let lhs = parse_term(inner.next().unwrap(), syntax)?;
+                let rhs = parse_term(inner.next().unwrap(), syntax)?;
+                prog.push(Expr::Assert(lhs, rhs));
+            }
+            Rule::rule_decl => {
+                let mut inner = pair.into_inner();
+                let lhs = inner.next().unwrap().as_str();
+                let rhs = inner.next().unwrap().as_str();
+                prog.push(Expr::Rule(RewriteRule::parse(lhs, rhs)?));
+            }
+            Rule::infix_decl => {
+                let (symbol, op) = parse_infix_decl(pair);
+                syntax.operators.insert(symbol, op);
+            }
+            Rule::macro_decl => {
+                let (name, m) = parse_macro_decl(pair, syntax)?;
+                syntax.macros.insert(name, m);
+            }
+            Rule::assignment => {
+                let (name, term) = parse_assignment(pair, syntax)?;
                 prog.push(Expr::Assignment(name, term));
             }
+            Rule::mutual => {
+                let defs = pair
+                    .into_inner()
+                    .map(|def| parse_assignment(def, syntax))
+                    .collect::<Result<_, _>>()?;
+                prog.extend(desugar_mutual(defs));
+            }
             // Parse a lambda calculus term
-            _ => prog.push(Expr::Term(parse_term(pair))),
+            _ => prog.push(Expr::Term(parse_term(pair, syntax)?)),
         }
     }
-    prog
+    Ok(prog)
 }
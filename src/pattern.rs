@@ -0,0 +1,213 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    error::Error,
+    eval::substitute,
+    parser::{parse_term_str, var, Term},
+};
+
+/// Subterms captured by the holes of a pattern, by hole name
+pub type Bindings = HashMap<String, Term>;
+
+/// A term with holes `?name` that match any subterm
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    Hole(String),
+    Variable(String),
+    Abstraction(String, Box<Pattern>),
+    Application(Box<Pattern>, Box<Pattern>),
+}
+
+impl Pattern {
+    /// Turn a term into a pattern where the free variables named in `holes` are holes
+    pub fn from_term(term: &Term, holes: &HashSet<String>) -> Self {
+        fn convert(term: &Term, holes: &HashSet<String>, bound: &mut Vec<String>) -> Pattern {
+            match term {
+                Term::Variable(v) if holes.contains(v) && !bound.contains(v) => {
+                    Pattern::Hole(v.clone())
+                }
+                Term::Variable(v) => Pattern::Variable(v.clone()),
+                Term::Abstraction(param, body) => {
+                    bound.push(param.clone());
+                    let body = convert(body, holes, bound);
+                    bound.pop();
+                    Pattern::Abstraction(param.clone(), Box::new(body))
+                }
+                Term::Application(f, x) => Pattern::Application(
+                    Box::new(convert(f, holes, bound)),
+                    Box::new(convert(x, holes, bound)),
+                ),
+            }
+        }
+        convert(term, holes, &mut Vec::new())
+    }
+}
+
+/// Parse a pattern written like a term, where `?name` is a hole
+/// A name used as a hole is a hole wherever it occurs free in the pattern.
+pub fn parse_pattern(input: &str) -> Result<Pattern, Error> {
+    let mut holes = HashSet::new();
+    let mut source = String::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '?' {
+            source.push(c);
+            continue;
+        }
+        let mut name = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '\'')
+        {
+            name.push(c);
+        }
+        source += &name;
+        holes.insert(name);
+    }
+    Ok(Pattern::from_term(&parse_term_str(&source)?, &holes))
+}
+
+/// Match a term against a pattern, returning the subterms captured by its holes
+/// Bound variables match up to α-equivalence, so `λx.?b` matches `λy.y` binding `b` to `y`.
+/// A hole used several times must capture the same subterm each time.
+pub fn matches(pattern: &Pattern, term: &Term) -> Option<Bindings> {
+    matches_under(pattern, term, &HashSet::new())
+}
+
+/// Match a term that occurs under binders for `bound_vars`
+/// A free variable of the pattern does not match a variable bound outside the term.
+pub fn matches_under(
+    pattern: &Pattern,
+    term: &Term,
+    bound_vars: &HashSet<String>,
+) -> Option<Bindings> {
+    /// Match under binders, where `renames` maps bound pattern variables to term variables
+    fn go(
+        pattern: &Pattern,
+        term: &Term,
+        bound_vars: &HashSet<String>,
+        renames: &mut Vec<(String, String)>,
+        bindings: &mut Bindings,
+    ) -> bool {
+        match (pattern, term) {
+            (Pattern::Hole(name), _) => match bindings.get(name) {
+                Some(bound) => bound == term,
+                None => {
+                    bindings.insert(name.clone(), term.clone());
+                    true
+                }
+            },
+            (Pattern::Variable(p), Term::Variable(t)) => {
+                // Both must be bound by the same pair of binders, or both be free and equal
+                let pattern_binder = renames.iter().rposition(|(from, _)| from == p);
+                let term_binder = renames.iter().rposition(|(_, to)| to == t);
+                pattern_binder == term_binder
+                    && (pattern_binder.is_some() || (p == t && !bound_vars.contains(t)))
+            }
+            (Pattern::Abstraction(p, pbody), Term::Abstraction(t, tbody)) => {
+                renames.push((p.clone(), t.clone()));
+                let matched = go(pbody, tbody, bound_vars, renames, bindings);
+                renames.pop();
+                matched
+            }
+            (Pattern::Application(pf, px), Term::Application(tf, tx)) => {
+                go(pf, tf, bound_vars, renames, bindings)
+                    && go(px, tx, bound_vars, renames, bindings)
+            }
+            _ => false,
+        }
+    }
+    let mut bindings = Bindings::new();
+    go(pattern, term, bound_vars, &mut Vec::new(), &mut bindings).then_some(bindings)
+}
+
+/// A rewrite rule `rule lhs => rhs`, replacing subterms that match `lhs` by `rhs`
+/// with its holes filled in by the subterms they captured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RewriteRule {
+    pub lhs: Pattern,
+    pub rhs: Pattern,
+}
+
+impl RewriteRule {
+    /// Parse both sides of a rule, failing if `rhs` has a hole that `lhs` does not
+    pub fn parse(lhs: &str, rhs: &str) -> Result<Self, Error> {
+        let lhs = parse_pattern(lhs)?;
+        let rhs = parse_pattern(rhs)?;
+        let lhs_holes = holes(&lhs);
+        if let Some(hole) = holes(&rhs).into_iter().find(|h| !lhs_holes.contains(h)) {
+            return Err(Error::UnboundVar(format!("?{}", hole)));
+        }
+        Ok(RewriteRule { lhs, rhs })
+    }
+
+    /// Rewrite a term under binders for `bound_vars` if it matches the left side of the rule
+    pub fn apply(&self, term: &Term, bound_vars: &HashSet<String>) -> Option<Term> {
+        let bindings = matches_under(&self.lhs, term, bound_vars)?;
+        // Holes become names that cannot be written, then are substituted without capture
+        fn template(pattern: &Pattern) -> Term {
+            match pattern {
+                Pattern::Hole(name) => var(&format!("?{}", name)),
+                Pattern::Variable(v) => var(v),
+                Pattern::Abstraction(param, body) => {
+                    Term::Abstraction(param.clone(), Box::new(template(body)))
+                }
+                Pattern::Application(f, x) => {
+                    Term::Application(Box::new(template(f)), Box::new(template(x)))
+                }
+            }
+        }
+        let mut holes = bindings.into_iter().collect::<Vec<_>>();
+        holes.sort_by(|a, b| a.0.cmp(&b.0));
+        Some(
+            holes
+                .iter()
+                .fold(template(&self.rhs), |term, (hole, value)| {
+                    substitute(&term, &format!("?{}", hole), value)
+                }),
+        )
+    }
+}
+
+/// Names of the holes in a pattern
+fn holes(pattern: &Pattern) -> HashSet<String> {
+    match pattern {
+        Pattern::Hole(name) => HashSet::from([name.clone()]),
+        Pattern::Variable(_) => HashSet::new(),
+        Pattern::Abstraction(_, body) => holes(body),
+        Pattern::Application(f, x) => &holes(f) | &holes(x),
+    }
+}
+
+/// Rewrite the leftmost-outermost subterm that one of the rules applies to
+/// Rules are tried in order at each subterm. Returns `None` if no rule applies anywhere.
+pub fn rewrite_step(term: &Term, rules: &[RewriteRule]) -> Option<Term> {
+    rewrite_under(term, rules, &mut HashSet::new())
+}
+
+/// Rewrite a term occurring under binders for `bound_vars`, like `normal_step`
+fn rewrite_under(
+    term: &Term,
+    rules: &[RewriteRule],
+    bound_vars: &mut HashSet<String>,
+) -> Option<Term> {
+    if let Some(rewritten) = rules.iter().find_map(|rule| rule.apply(term, bound_vars)) {
+        return Some(rewritten);
+    }
+    match term {
+        Term::Variable(_) => None,
+        Term::Abstraction(param, body) => {
+            let shadowed = !bound_vars.insert(param.clone());
+            let body = rewrite_under(body, rules, bound_vars);
+            if !shadowed {
+                bound_vars.remove(param);
+            }
+            Some(Term::Abstraction(param.clone(), Box::new(body?)))
+        }
+        Term::Application(f, x) => match rewrite_under(f, rules, bound_vars) {
+            Some(f) => Some(Term::Application(Box::new(f), x.clone())),
+            None => Some(Term::Application(
+                f.clone(),
+                Box::new(rewrite_under(x, rules, bound_vars)?),
+            )),
+        },
+    }
+}
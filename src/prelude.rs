@@ -0,0 +1,39 @@
+use crate::{
+    analysis::check_definition_order,
+    config::Config,
+    error::Error,
+    parser::{parse_prog, preprocess},
+};
+
+/// Standard library loaded at startup, embedded so the binary works without any files
+pub const PRELUDE: &str = include_str!("./std.lc");
+
+/// Read the prelude source selected by `--prelude-path` or the embedded prelude
+/// Returns `None` if the prelude is disabled with `--no-prelude`.
+pub fn prelude_source(config: &Config) -> Option<Result<String, Error>> {
+    if config.no_prelude {
+        return None;
+    }
+    Some(match &config.prelude_path {
+        Some(path) => std::fs::read_to_string(path).map_err(Error::from),
+        None => Ok(PRELUDE.to_string()),
+    })
+}
+
+/// Configuration to evaluate the prelude with, derived from the one of the program
+///
+/// The prelude is written without regard to `--strict-app-arity` and its definitions are not
+/// printed, so the check, step counts and profiling are turned off for it.
+pub fn prelude_config(config: &Config) -> Config {
+    Config {
+        strict_app_arity: false,
+        show_steps: false,
+        profile: false,
+        ..config.clone()
+    }
+}
+
+/// Check that every definition of a prelude only uses the definitions before it
+pub fn check_prelude(source: &str) -> Result<(), Error> {
+    check_definition_order(&parse_prog(&preprocess(source))?)
+}
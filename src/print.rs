@@ -1,15 +1,68 @@
 use std::io::Write;
 
-use crate::Term;
+use crate::{
+    config::Config,
+    equiv::{alpha_eq, canonicalize_names, short_names},
+    eval::{Dir, Env, RedexPath},
+    numeral::{decode_literal, from_church_numeral, numeral_literal},
+    parser::TYPE_MARKER,
+    Term,
+};
 
 const DARK_GRAY: &str = "\x1b[90m";
 const YELLOW: &str = "\x1b[33m";
-const CYAN: &str = "\x1b[36m";
 const GREEN: &str = "\x1b[32m";
 const PINK: &str = "\x1b[35m";
 const ITALIC: &str = "\x1b[3m";
+const CYAN_ITALIC: &str = "\x1b[36m\x1b[3m";
+const DIFF_OLD: &str = "\x1b[1;31m";
+const DIFF_NEW: &str = "\x1b[1;32m";
 const RESET: &str = "\x1b[0m";
 
+/// When to color printed output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ColorChoice {
+    Always,
+    /// Only when writing to a terminal
+    #[default]
+    Auto,
+    Never,
+}
+
+impl ColorChoice {
+    /// Check if output written to a sink should be colored
+    pub fn enabled(self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Remove ANSI color codes from printed output
+pub fn strip_color(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            chars.by_ref().find(|c| *c == 'm');
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Keep the color codes of printed output only if colors are `enabled`
+pub fn paint(s: &str, enabled: bool) -> String {
+    if enabled {
+        s.to_string()
+    } else {
+        strip_color(s)
+    }
+}
+
 pub fn line(len: usize) {
     println!("{}{}{}", DARK_GRAY, "-".repeat(len), RESET);
 }
@@ -22,40 +75,498 @@ pub fn pause(s: &str) {
     print!("\x1b[2K"); // Clear the line
 }
 
-pub fn var(v: &str) -> String {
+/// Color used to print a variable name
+fn var_color(v: &str) -> &'static str {
     match v {
         // booleans
-        "true" => format!("{CYAN}{ITALIC}true{RESET}"),
-        "false" => format!("{CYAN}{ITALIC}false{RESET}"),
+        "true" | "false" => CYAN_ITALIC,
         // function names
-        _ if char::is_uppercase(v.chars().next().unwrap()) => {
-            format!("{PINK}{}{RESET}", v)
-        }
+        _ if char::is_uppercase(v.chars().next().unwrap()) => PINK,
         // digits
-        _ if v.chars().all(char::is_numeric) => {
-            format!("{GREEN}{}{RESET}", v)
-        }
+        _ if v.chars().all(char::is_numeric) => GREEN,
         // variable names
-        _ => format!("{ITALIC}{}{RESET}", v),
+        _ => ITALIC,
+    }
+}
+
+pub fn var(v: &str) -> String {
+    format!("{}{}{RESET}", var_color(v), v)
+}
+
+/// Notation used to print abstractions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Style {
+    /// `λx.body`
+    #[default]
+    Unicode,
+    /// `\x.body`
+    Ascii,
+    /// `\x -> body`
+    Haskell,
+    /// `\lambda x. body` in LaTeX math mode, with thin spaces between applied terms
+    Latex,
+}
+
+/// Printed text that only keeps its first `limit` visible characters
+/// Text past the limit is counted but not stored, so huge terms are never rendered in full.
+struct Output {
+    text: String,
+    /// Number of visible characters pushed, including those past the limit
+    len: usize,
+    limit: usize,
+    /// Print application spines like `f a b` instead of `((f a) b)`, see `spine`
+    flat_spines: bool,
+}
+
+impl Output {
+    fn new(limit: usize) -> Self {
+        Output {
+            text: String::new(),
+            len: 0,
+            limit,
+            flat_spines: false,
+        }
+    }
+
+    /// Append text in the given color, or without color if it is empty
+    fn push(&mut self, color: &str, s: &str) {
+        let room = self.limit.saturating_sub(self.len);
+        self.len += s.chars().count();
+        if room == 0 {
+            return;
+        }
+        let end = s.char_indices().nth(room).map_or(s.len(), |(i, _)| i);
+        self.text.push_str(color);
+        self.text.push_str(&s[..end]);
+        if !color.is_empty() {
+            self.text.push_str(RESET);
+        }
+    }
+
+    fn binder(&mut self, param: &str, style: Style) {
+        let is_type = param.starts_with(TYPE_MARKER);
+        match style {
+            Style::Unicode if is_type => self.push(YELLOW, "Λ"),
+            Style::Unicode => self.push(YELLOW, "λ"),
+            Style::Ascii | Style::Haskell => self.push(YELLOW, "\\"),
+            Style::Latex if is_type => self.push(YELLOW, "\\Lambda "),
+            Style::Latex => self.push(YELLOW, "\\lambda "),
+        }
+        self.name(param, style);
+        match style {
+            Style::Unicode | Style::Ascii => self.push(DARK_GRAY, "."),
+            Style::Haskell => {
+                self.push("", " ");
+                self.push(DARK_GRAY, "->");
+                self.push("", " ");
+            }
+            Style::Latex => {
+                self.push(DARK_GRAY, ".");
+                self.push("", " ");
+            }
+        }
     }
+
+    /// Append a variable name, set in italics as a single symbol in LaTeX if it is longer
+    fn name(&mut self, v: &str, style: Style) {
+        let v = unmarked(v);
+        let letters = v.trim_end_matches('\'').split('_').next().unwrap_or(v);
+        if style == Style::Latex && letters.chars().count() > 1 && numeral_literal(v).is_none() {
+            self.push(var_color(v), &format!("\\mathit{{{}}}", v));
+        } else {
+            self.push(var_color(v), v);
+        }
+    }
+
+    /// Render a term, collapsing repeated applications if `abbreviate` is set
+    fn term(&mut self, t: &Term, style: Style, abbreviate: bool) {
+        match t {
+            Term::Variable(v) => self.name(v, style),
+            Term::Abstraction(param, body) => {
+                self.binder(param, style);
+                self.term(body, style, abbreviate);
+            }
+            Term::Application(_, _) if self.flat_spines => self.spine(t, style, true),
+            Term::Application(f, x) => {
+                // Count how many times `f` is applied in a row
+                let mut n = 1;
+                let mut arg = x.as_ref();
+                while let Term::Application(g, y) = arg {
+                    if !abbreviate || g != f {
+                        break;
+                    }
+                    n += 1;
+                    arg = y;
+                }
+                if n < ABBREVIATE_MIN {
+                    arg = x;
+                }
+                self.push(DARK_GRAY, "(");
+                self.term(f, style, abbreviate);
+                if n >= ABBREVIATE_MIN {
+                    self.push(DARK_GRAY, "^");
+                    self.push(GREEN, &n.to_string());
+                }
+                match style {
+                    Style::Latex => self.push("", "\\,"),
+                    _ => self.push("", " "),
+                }
+                self.term(arg, style, abbreviate);
+                self.push(DARK_GRAY, ")");
+            }
+        }
+    }
+
+    /// Render a left-nested application `((f a) b)` as the flat spine `f a b`
+    /// Only arguments that are not variables and a head abstraction are parenthesized,
+    /// and the whole spine too if it is `wrapped` inside another term.
+    fn spine(&mut self, t: &Term, style: Style, wrapped: bool) {
+        let mut args = Vec::new();
+        let mut head = t;
+        while let Term::Application(f, x) = head {
+            args.push(x.as_ref());
+            head = f;
+        }
+        if wrapped {
+            self.push(DARK_GRAY, "(");
+        }
+        self.atom(head, style);
+        for arg in args.into_iter().rev() {
+            match style {
+                Style::Latex => self.push("", "\\,"),
+                _ => self.push("", " "),
+            }
+            self.atom(arg, style);
+        }
+        if wrapped {
+            self.push(DARK_GRAY, ")");
+        }
+    }
+
+    /// Render a term in a spine, in parentheses unless it is a variable
+    fn atom(&mut self, t: &Term, style: Style) {
+        match t {
+            Term::Variable(_) | Term::Application(_, _) => self.term(t, style, false),
+            Term::Abstraction(_, _) => {
+                self.push(DARK_GRAY, "(");
+                self.term(t, style, false);
+                self.push(DARK_GRAY, ")");
+            }
+        }
+    }
+}
+
+/// Print the binder of an abstraction, such as `λx.`
+fn binder(param: &str, style: Style) -> String {
+    let mut out = Output::new(usize::MAX);
+    out.binder(param, style);
+    out.text
 }
 
 /// Pretty print a term
 pub fn term(t: &Term) -> String {
+    term_styled(t, Style::Unicode)
+}
+
+/// Pretty print a term using the given notation for abstractions
+pub fn term_styled(t: &Term, style: Style) -> String {
+    let mut out = Output::new(usize::MAX);
+    out.term(t, style, false);
+    out.text
+}
+
+/// Pretty print a term, writing application spines `((f a) b)` as `f a b`
+/// The output parses back to the same term.
+pub fn term_flat(t: &Term) -> String {
+    let mut out = Output::new(usize::MAX);
+    out.flat_spines = true;
     match t {
-        Term::Variable(v) => var(v),
+        Term::Application(_, _) => out.spine(t, Style::Unicode, false),
+        _ => out.term(t, Style::Unicode, false),
+    }
+    out.text
+}
+
+/// Minimum number of repeated applications that are abbreviated
+const ABBREVIATE_MIN: usize = 3;
+
+/// Pretty print a term, collapsing repeated applications `(f (f (f x)))` into `(f^3 x)`
+/// This makes large Church numerals and similar repetitive structures readable.
+pub fn term_abbreviated(t: &Term) -> String {
+    let mut out = Output::new(usize::MAX);
+    out.term(t, Style::Unicode, true);
+    out.text
+}
+
+/// Replace subterms that are α-equivalent to a definition in env by the defined name
+/// Names defined as a numeral literal, like `ten = 10;`, replace the Church numeral.
+/// Ambiguous matches prefer the shortest name.
+pub fn with_aliases(t: &Term, env: &Env) -> Term {
+    if !matches!(t, Term::Variable(_)) {
+        let numeral = from_church_numeral(t);
+        let alias = env
+            .iter()
+            .filter(|(_, value)| match value {
+                Term::Variable(v) => numeral.is_some() && numeral_literal(v) == numeral,
+                _ => alpha_eq(t, value),
+            })
+            .map(|(name, _)| name)
+            .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)));
+        if let Some(name) = alias {
+            return Term::Variable(name.clone());
+        }
+    }
+    match t {
+        Term::Variable(_) => t.clone(),
         Term::Abstraction(param, body) => {
-            let body = term(body);
-            format!("{YELLOW}λ{RESET}{}{DARK_GRAY}.{RESET}{}", var(param), body)
+            Term::Abstraction(param.clone(), Box::new(with_aliases(body, env)))
         }
-        Term::Application(f, x) => format!(
-            "{DARK_GRAY}({RESET}{} {}{DARK_GRAY}){RESET}",
-            term(f),
-            term(x)
+        Term::Application(f, x) => Term::Application(
+            Box::new(with_aliases(f, env)),
+            Box::new(with_aliases(x, env)),
         ),
     }
 }
 
+/// Pretty print a resulting term using the display options in `config`
+/// Definitions in `env` are used as aliases with `--use-aliases`.
+pub fn result(t: &Term, env: &Env, config: &Config) -> String {
+    let renamed;
+    let t = if config.canonical {
+        renamed = canonicalize_names(t);
+        &renamed
+    } else if config.short_names {
+        renamed = short_names(t);
+        &renamed
+    } else {
+        t
+    };
+    if let Some(literal) = config.decode.then(|| decode_literal(t)).flatten() {
+        return var(&literal);
+    }
+    let aliased;
+    let t = if config.use_aliases {
+        aliased = with_aliases(t, env);
+        &aliased
+    } else {
+        t
+    };
+    let mut out = Output::new(config.max_output_size.unwrap_or(usize::MAX));
+    out.flat_spines = config.flat_spines;
+    match t {
+        Term::Application(_, _) if config.flat_spines => out.spine(t, config.style, false),
+        _ => out.term(t, config.style, config.abbreviate),
+    }
+    if out.len > out.limit {
+        out.text += &format!("{DARK_GRAY}…{RESET} ({} characters)", out.len);
+    }
+    match arity(t) {
+        n if config.show_arity && n > 0 => format!("{DARK_GRAY}<fn/{}>{RESET} {}", n, out.text),
+        _ => out.text,
+    }
+}
+
+/// Number of leading binders of a term, such as 2 for `λx.λy.(x y)`
+fn arity(t: &Term) -> usize {
+    match t {
+        Term::Abstraction(_, body) => 1 + arity(body),
+        _ => 0,
+    }
+}
+
+/// Number of spaces to indent broken subterms with
+const INDENT: usize = 2;
+
+/// Length of a term when printed on a single line, excluding color codes
+fn width(t: &Term) -> usize {
+    match t {
+        Term::Variable(v) => v.chars().count(),
+        Term::Abstraction(param, body) => param.chars().count() + 2 + width(body),
+        Term::Application(f, x) => width(f) + width(x) + 3,
+    }
+}
+
+/// Pretty print a term, breaking it across indented lines
+/// whenever a subterm does not fit within `max_width` columns.
+pub fn term_wrapped(t: &Term, max_width: usize) -> String {
+    fn layout(t: &Term, max_width: usize, indent: usize) -> String {
+        if indent + width(t) <= max_width {
+            return term(t);
+        }
+        let pad = " ".repeat(indent + INDENT);
+        match t {
+            Term::Variable(v) => var(v),
+            Term::Abstraction(_, _) => {
+                // Keep leading binders together on the first line
+                let mut head = String::new();
+                let mut body = t;
+                while let Term::Abstraction(param, inner) = body {
+                    head += &binder(param, Style::Unicode);
+                    body = inner;
+                }
+                let body = layout(body, max_width, indent + INDENT);
+                format!("{}\n{}{}", head, pad, body)
+            }
+            Term::Application(f, x) => format!(
+                "{DARK_GRAY}({RESET}{}\n{}{}{DARK_GRAY}){RESET}",
+                layout(f, max_width, indent + 1),
+                pad,
+                layout(x, max_width, indent + INDENT)
+            ),
+        }
+    }
+    layout(t, max_width, 0)
+}
+
+/// Name of a variable as written, without the marker of type abstraction parameters
+fn unmarked(v: &str) -> &str {
+    v.strip_prefix(TYPE_MARKER).unwrap_or(v)
+}
+
+/// Print the binder of an abstraction without color codes, `Λ` for type abstractions
+fn plain_binder(param: &str) -> String {
+    match param.strip_prefix(TYPE_MARKER) {
+        Some(param) => format!("Λ{}.", param),
+        None => format!("λ{}.", param),
+    }
+}
+
+/// Pretty print a term without any color codes, regardless of `--color`
+pub fn term_plain(t: &Term) -> String {
+    match t {
+        Term::Variable(v) => unmarked(v).to_string(),
+        Term::Abstraction(param, body) => format!("{}{}", plain_binder(param), term_plain(body)),
+        Term::Application(f, x) => format!("({} {})", term_plain(f), term_plain(x)),
+    }
+}
+
+/// Print a term like `term_plain`, wrapping the subterm at `path` in `open` and `close`
+pub fn term_marked(t: &Term, path: &[Dir], open: &str, close: &str) -> String {
+    match (t, path) {
+        (_, []) => format!("{}{}{}", open, term_plain(t), close),
+        (Term::Abstraction(param, body), [Dir::Body, rest @ ..]) => {
+            format!(
+                "{}{}",
+                plain_binder(param),
+                term_marked(body, rest, open, close)
+            )
+        }
+        (Term::Application(f, x), [dir @ (Dir::Fun | Dir::Arg), rest @ ..]) => {
+            let (f, x) = match dir {
+                Dir::Fun => (term_marked(f, rest, open, close), term_plain(x)),
+                _ => (term_plain(f), term_marked(x, rest, open, close)),
+            };
+            format!("({} {})", f, x)
+        }
+        // The path leads out of the term, so there is nothing to mark
+        _ => term_plain(t),
+    }
+}
+
+/// Pretty print two terms on separate lines, highlighting the subterms where they differ
+/// Differing subterms of `a` are shown in red and those of `b` in green.
+pub fn term_diff(a: &Term, b: &Term) -> String {
+    /// Render both sides, returning the printed `a` and `b`
+    fn diff(a: &Term, b: &Term) -> (String, String) {
+        match (a, b) {
+            _ if a == b => (term(a), term(b)),
+            (Term::Abstraction(x, e1), Term::Abstraction(y, e2)) if x == y => {
+                let (e1, e2) = diff(e1, e2);
+                let binder = binder(x, Style::Unicode);
+                (format!("{}{}", binder, e1), format!("{}{}", binder, e2))
+            }
+            (Term::Application(f1, x1), Term::Application(f2, x2)) => {
+                let (f1, f2) = diff(f1, f2);
+                let (x1, x2) = diff(x1, x2);
+                let app = |f, x| format!("{DARK_GRAY}({RESET}{} {}{DARK_GRAY}){RESET}", f, x);
+                (app(f1, x1), app(f2, x2))
+            }
+            _ => (
+                format!("{DIFF_OLD}{}{RESET}", term_plain(a)),
+                format!("{DIFF_NEW}{}{RESET}", term_plain(b)),
+            ),
+        }
+    }
+    let (a, b) = diff(a, b);
+    format!("{}\n{}", a, b)
+}
+
+/// Print a term and the normal form it reduces to, such as `((λx.x) y) ↠ y`
+pub fn normalized(t: &Term, normal: &Term) -> String {
+    format!("{} {DARK_GRAY}↠{RESET} {}", term(t), term(normal))
+}
+
+/// Print a parenthesized remark following a result, such as `(3 steps)`
+pub fn note(s: &str) -> String {
+    format!("{DARK_GRAY}({}){RESET}", s)
+}
+
 pub fn assign(name: &str, t: &Term) -> String {
     format!("{} = {}{DARK_GRAY};{RESET}", var(name), term(t))
 }
+
+/// Render the steps of a reduction trace as a standalone HTML page
+/// Every step is a `<section class="step">` showing the term with the redex that it contracts
+/// highlighted. The script shows one step at a time, navigated with buttons or arrow keys.
+/// `terminated` tells whether the last step is the normal form.
+pub fn trace_html(steps: &[(Term, Option<RedexPath>)], terminated: bool) -> String {
+    let mut html = String::from(TRACE_HTML_HEAD);
+    for (i, (t, redex)) in steps.iter().enumerate() {
+        let t = match redex {
+            Some(path) => term_marked(t, path, "<mark>", "</mark>"),
+            None => term_plain(t),
+        };
+        let label = match i {
+            0 => "input".to_string(),
+            i if i + 1 == steps.len() && terminated => format!("step {}, normal form", i),
+            i => format!("step {}", i),
+        };
+        html += &format!(
+            "<section class=\"step\"><h2>{}</h2><pre>{}</pre></section>\n",
+            label, t
+        );
+    }
+    if !terminated {
+        html += "<p>Stopped before reaching a normal form.</p>\n";
+    }
+    html + TRACE_HTML_TAIL
+}
+
+const TRACE_HTML_HEAD: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Reduction trace</title>
+<style>
+body { font-family: sans-serif; margin: 2em; }
+pre { font-size: 1.3em; white-space: pre-wrap; word-break: break-all; }
+mark { background: #ffe08a; border-radius: 3px; }
+</style>
+</head>
+<body>
+<nav>
+<button id="prev">&larr; Previous</button>
+<span id="position"></span>
+<button id="next">Next &rarr;</button>
+</nav>
+"#;
+
+const TRACE_HTML_TAIL: &str = r#"<script>
+const steps = document.querySelectorAll(".step");
+let current = 0;
+function show(i) {
+    current = Math.max(0, Math.min(steps.length - 1, i));
+    steps.forEach((step, j) => step.hidden = j !== current);
+    document.getElementById("position").textContent = (current + 1) + " / " + steps.length;
+}
+document.getElementById("prev").onclick = () => show(current - 1);
+document.getElementById("next").onclick = () => show(current + 1);
+document.addEventListener("keydown", e => {
+    if (e.key === "ArrowLeft") show(current - 1);
+    if (e.key === "ArrowRight") show(current + 1);
+});
+show(0);
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,79 @@
+use std::{
+    cell::Cell,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Phases of running a program that are timed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Preprocessing, checking and parsing the source
+    Parsing,
+    /// Unfolding definitions into terms before they are reduced
+    Inlining,
+    /// Reducing terms to normal form
+    Reduction,
+}
+
+/// Time spent in each phase of running a program
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Profile {
+    pub parsing: Duration,
+    pub inlining: Duration,
+    pub reduction: Duration,
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "parsing {:?}, inlining {:?}, reduction {:?}",
+            self.parsing, self.inlining, self.reduction
+        )
+    }
+}
+
+thread_local! {
+    /// Durations of the innermost `collect` running on this thread, if any
+    static PROFILE: Cell<Option<Profile>> = const { Cell::new(None) };
+}
+
+/// Run `f`, returning its result with the time spent in each phase while it ran
+/// Like `stats::collect`, time measured by a nested collection also counts for the outer one.
+pub fn collect<T>(f: impl FnOnce() -> T) -> (T, Profile) {
+    let outer = PROFILE.replace(Some(Profile::default()));
+    let result = f();
+    let profile = PROFILE.replace(outer).unwrap_or_default();
+    for (phase, elapsed) in [
+        (Phase::Parsing, profile.parsing),
+        (Phase::Inlining, profile.inlining),
+        (Phase::Reduction, profile.reduction),
+    ] {
+        add(phase, elapsed);
+    }
+    (result, profile)
+}
+
+/// Run `f`, adding the time it takes to `phase` if a profile is being collected
+pub(crate) fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    if PROFILE.get().is_none() {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    add(phase, start.elapsed());
+    result
+}
+
+fn add(phase: Phase, elapsed: Duration) {
+    PROFILE.with(|profile| {
+        if let Some(mut p) = profile.get() {
+            match phase {
+                Phase::Parsing => p.parsing += elapsed,
+                Phase::Inlining => p.inlining += elapsed,
+                Phase::Reduction => p.reduction += elapsed,
+            }
+            profile.set(Some(p));
+        }
+    });
+}
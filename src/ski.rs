@@ -0,0 +1,51 @@
+use crate::{eval::free_vars, parser::Term};
+
+/// Compile a term into combinatory logic using only the `S`, `K` and `I` combinators
+/// The combinators are represented as the free variables `S`, `K` and `I`,
+/// so terms should not use these names as free variables themselves.
+///
+/// See https://en.wikipedia.org/wiki/Combinatory_logic#Completeness_of_the_S-K_basis.
+pub fn to_ski(term: &Term) -> Term {
+    /// Eliminate the variable `var` from a term that contains no abstractions
+    fn abstract_var(var: &str, term: &Term) -> Term {
+        let combinator = |c: &str| Box::new(Term::Variable(c.to_string()));
+        match term {
+            // T[λx.x] = I
+            Term::Variable(v) if v == var => *combinator("I"),
+            // T[λx.E] = (K T[E])  (x not in free_vars(E))
+            _ if !free_vars(term).contains(var) => {
+                Term::Application(combinator("K"), Box::new(term.clone()))
+            }
+            // T[λx.(E1 E2)] = ((S T[λx.E1]) T[λx.E2])
+            Term::Application(e1, e2) => Term::Application(
+                Box::new(Term::Application(
+                    combinator("S"),
+                    Box::new(abstract_var(var, e1)),
+                )),
+                Box::new(abstract_var(var, e2)),
+            ),
+            _ => unreachable!("abstractions are compiled before they are abstracted"),
+        }
+    }
+    match term {
+        Term::Variable(_) => term.clone(),
+        Term::Abstraction(param, body) => abstract_var(param, &to_ski(body)),
+        Term::Application(e1, e2) => Term::Application(Box::new(to_ski(e1)), Box::new(to_ski(e2))),
+    }
+}
+
+/// Compile a term to SKI combinators and print it without any lambdas
+/// Application is left-associative, so only arguments that are applications are parenthesized.
+pub fn to_combinator_string(term: &Term) -> String {
+    fn render(term: &Term) -> String {
+        match term {
+            Term::Variable(v) => v.clone(),
+            Term::Application(f, x) => match x.as_ref() {
+                Term::Application(_, _) => format!("{} ({})", render(f), render(x)),
+                _ => format!("{} {}", render(f), render(x)),
+            },
+            Term::Abstraction(_, _) => unreachable!("SKI terms contain no abstractions"),
+        }
+    }
+    render(&to_ski(term))
+}
@@ -0,0 +1,55 @@
+use std::{cell::Cell, fmt};
+
+/// Counters of the work done by substitution while reducing terms
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Calls of `substitute`, including those on subterms
+    pub substitutions: usize,
+    /// Bound variables renamed to avoid capturing a free variable
+    pub renames: usize,
+    /// Term nodes built by substitution and renaming, including copies
+    pub allocations: usize,
+    /// Traversals of substituted values by `substitute` to collect their free variables
+    pub free_var_scans: usize,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} substitutions, {} renames, {} allocations",
+            self.substitutions, self.renames, self.allocations
+        )
+    }
+}
+
+thread_local! {
+    /// Counters of the innermost `collect` running on this thread, if any
+    static STATS: Cell<Option<Stats>> = const { Cell::new(None) };
+}
+
+/// Run `f`, returning its result with the statistics of the reductions it performed
+/// Nothing is counted outside of `collect`, so reductions are not slowed down by default.
+pub fn collect<T>(f: impl FnOnce() -> T) -> (T, Stats) {
+    let outer = STATS.replace(Some(Stats::default()));
+    let result = f();
+    let stats = STATS.replace(outer).unwrap_or_default();
+    // Work counted by a nested collection is also part of the enclosing one
+    record(|s| {
+        s.substitutions += stats.substitutions;
+        s.renames += stats.renames;
+        s.allocations += stats.allocations;
+        s.free_var_scans += stats.free_var_scans;
+    });
+    (result, stats)
+}
+
+/// Update the counters if statistics are being collected
+pub(crate) fn record(update: impl FnOnce(&mut Stats)) {
+    STATS.with(|stats| {
+        if let Some(mut s) = stats.get() {
+            update(&mut s);
+            stats.set(Some(s));
+        }
+    });
+}
@@ -1,10 +1,51 @@
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{
+        cell::RefCell,
+        collections::{HashMap, HashSet},
+    };
+
+    use clap::Parser;
+    use rustyline::history::{FileHistory, History};
 
     use crate::{
-        eval::{eval_expr, inline_vars},
-        parser::{parse_prog, Expr, Term},
+        analysis::{
+            check_definition_order, duplication_report, env_dependencies, env_graph_dot, is_closed,
+            is_recursive, might_diverge, term_size,
+        },
+        binary::{from_bytes, to_bytes, DecodeError},
+        cache::ResultCache,
+        completion::complete,
+        config::Config,
+        equiv::{alpha_eq, beta_eta_eq, definitional_eq, search, short_names},
+        error::Error,
+        eval::{
+            applicative_order, beta_reduce, contract_at, eta_expand, eta_reduce, eval_expr,
+            eval_open, eval_prog, eval_steps, free_vars, fresh_var, inline_vars, is_normal_form,
+            normal_form, normal_order, redexes, reduce_bounded, reduce_head, reduce_step,
+            reduce_step_explained, reduce_to_normal_form, reduce_with, run_entry, run_prog,
+            substitute, subterm_normal_forms, trace, trace_redexes, verify, verify_with,
+            Conversion, Dir, Env, Explanation, PrinterFn, RedexPath, ReduceFn, Strategy,
+            DEFAULT_STEP_LIMIT,
+        },
+        history::{load_history, save_history},
+        interpreter::Interpreter,
+        lazy::eval_lazy,
+        numeral::{from_church_numeral, from_signed_numeral, to_church_numeral, NUMERAL_CAP},
+        parser::{
+            abs, app, assign, check_app_arity, check_nesting, debug_ast, grammar_check, parse_prog,
+            parse_term_str, preprocess, var, Expr, Term, DEFAULT_MAX_NESTING,
+        },
+        pattern::{matches, parse_pattern, rewrite_step, RewriteRule},
+        prelude::{check_prelude, prelude_config, prelude_source, PRELUDE},
+        print,
+        print::{strip_color, ColorChoice, Style},
+        ski::{to_combinator_string, to_ski},
+        stats::{collect, Stats},
+        token::{tokenize, TokenKind},
+        undo::UndoStack,
+        visit::{map, walk},
+        warning::{reported, Warning, WarningKind},
         PRINT_NONE,
     };
 
@@ -13,14 +54,30 @@ mod tests {
             match self {
                 Expr::Assignment(_, term) => term,
                 Expr::Term(term) => term,
+                Expr::Import(_, _) => panic!("import has no term"),
+                Expr::Assert(_, _) => panic!("assertion has no term"),
+                Expr::Rule(_) => panic!("rule has no term"),
             }
         }
     }
 
+    thread_local! {
+        /// Lines printed by `PRINT_CAPTURE` on the current test thread
+        static OUTPUT: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Printer that records output instead of writing it to stdout
+    const PRINT_CAPTURE: PrinterFn = |t| OUTPUT.with(|o| o.borrow_mut().push(strip_color(&t)));
+
+    /// Take all lines recorded by `PRINT_CAPTURE` so far
+    fn captured() -> Vec<String> {
+        OUTPUT.with(|o| o.take())
+    }
+
     #[test]
     fn test_parse() {
         let input = "x = y; λx. (x y); x y;";
-        let terms = parse_prog(input);
+        let terms = parse_prog(input).unwrap();
         assert_eq!(
             &terms,
             &[
@@ -43,7 +100,7 @@ mod tests {
     #[test]
     fn test_multi_app() {
         let input = "λx. λy. λz. ((x y) z);";
-        let terms = parse_prog(input);
+        let terms = parse_prog(input).unwrap();
         assert_eq!(
             &terms,
             &[Expr::Term(Term::Abstraction(
@@ -69,10 +126,24 @@ mod tests {
     fn test_eval() {
         let mut env = HashMap::new();
         let input = "x = λx. (x y); x y;";
-        let prog = parse_prog(input);
+        let prog = parse_prog(input).unwrap();
         assert_eq!(prog.len(), 2);
-        eval_expr(&prog[0], &mut env, false, PRINT_NONE);
-        let result = eval_expr(&prog[1], &mut env, false, PRINT_NONE);
+        eval_expr(
+            &prog[0],
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        let result = eval_expr(
+            &prog[1],
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+            &mut Vec::new(),
+        )
+        .unwrap();
         assert_eq!(
             result,
             Term::Application(
@@ -89,12 +160,2468 @@ mod tests {
         let mut env = HashMap::new();
         let input = "A = λx. (A x); A y;";
         let expected = "(λx. (A x)) y";
-        let prog = parse_prog(input);
-        let binding = parse_prog(expected).pop().unwrap();
+        let prog = parse_prog(input).unwrap();
+        let binding = parse_prog(expected).unwrap().pop().unwrap();
         let prog_expected = binding.term();
         assert_eq!(prog.len(), 2);
-        eval_expr(&prog[0], &mut env, false, PRINT_NONE);
+        eval_expr(
+            &prog[0],
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+            &mut Vec::new(),
+        )
+        .unwrap();
         let inlined = inline_vars(prog[1].term(), &env);
         assert_eq!(&inlined, prog_expected);
     }
+
+    #[test]
+    fn test_config_parse() {
+        let config = Config::try_parse_from(["lambda", "--verbose", "prog.lc"]).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                file: Some("prog.lc".to_string()),
+                verbose: true,
+                ..Default::default()
+            }
+        );
+        let config = Config::try_parse_from(["lambda"]).unwrap();
+        assert_eq!(config, Config::default());
+        assert!(Config::try_parse_from(["lambda", "a.lc", "b.lc"]).is_err());
+    }
+
+    #[test]
+    fn test_eta_conversion() {
+        let f = Term::Variable("f".to_string());
+        let expanded = eta_expand(&f);
+        let expected = parse_prog("λx. (f x);").unwrap().pop().unwrap();
+        assert_eq!(&expanded, expected.term());
+        assert_eq!(eta_reduce(&expanded), f);
+        // The fresh variable must not capture a free variable of the term
+        let fx = parse_prog("f x;").unwrap().pop().unwrap();
+        let expanded = eta_expand(fx.term());
+        let expected = parse_prog("λx'. (f x x');").unwrap().pop().unwrap();
+        assert_eq!(&expanded, expected.term());
+        assert_eq!(&eta_reduce(&expanded), fx.term());
+        // λx.(x x) is not an η-redex since x occurs free in the function
+        let omega = parse_prog("λx. (x x);").unwrap().pop().unwrap();
+        assert_eq!(&eta_reduce(omega.term()), omega.term());
+    }
+
+    #[test]
+    fn test_free_vars_deep() {
+        let depth = 50_000;
+        let mut term = Term::Variable("x".to_string());
+        for i in 0..depth {
+            term = Term::Application(
+                Box::new(term),
+                Box::new(Term::Variable(format!("v{}", i % 3))),
+            );
+        }
+        let term = Term::Abstraction("v0".to_string(), Box::new(term));
+        let vars = free_vars(&term);
+        let mut vars = vars.into_iter().collect::<Vec<_>>();
+        vars.sort();
+        assert_eq!(vars, ["v1", "v2", "x"]);
+        // Dismantle the term iteratively to avoid overflowing on drop
+        let Term::Abstraction(_, mut term) = term else {
+            unreachable!()
+        };
+        while let Term::Application(lhs, _) = *term {
+            term = lhs;
+        }
+    }
+
+    #[test]
+    fn test_history_persistence() {
+        let path = std::env::temp_dir().join(format!("lambda_history_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // A missing history file is not an error
+        let mut history = FileHistory::new();
+        load_history(&mut history, &path).unwrap();
+        assert!(history.is_empty());
+        history.add("Id = λx.x").unwrap();
+        history.add("Id y").unwrap();
+        save_history(&mut history, &path).unwrap();
+
+        let mut restored = FileHistory::new();
+        load_history(&mut restored, &path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(restored.iter().collect::<Vec<_>>(), ["Id = λx.x", "Id y"]);
+    }
+
+    #[test]
+    fn test_alpha_eq() {
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        assert!(alpha_eq(&term("λx.λy.(x y);"), &term("λa.λb.(a b);")));
+        assert!(alpha_eq(&term("λx.(x z);"), &term("λy.(y z);")));
+        assert!(!alpha_eq(&term("λx.λy.x;"), &term("λx.λy.y;")));
+        assert!(!alpha_eq(&term("λx.(x z);"), &term("λz.(z z);")));
+    }
+
+    #[test]
+    fn test_definitional_eq() {
+        let mut env = HashMap::new();
+        let input = "Succ = λn.λf.λx.(f ((n f) x)); One = λf.λx.(f x); Two = λf.λx.(f (f x));";
+        for expr in parse_prog(input).unwrap() {
+            eval_expr(
+                &expr,
+                &mut env,
+                &Config::default(),
+                PRINT_NONE,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        }
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        assert!(definitional_eq(
+            &term("Succ One;"),
+            &term("Two;"),
+            &env,
+            DEFAULT_STEP_LIMIT
+        ));
+        assert!(!definitional_eq(
+            &term("Succ Two;"),
+            &term("Two;"),
+            &env,
+            DEFAULT_STEP_LIMIT
+        ));
+    }
+
+    #[test]
+    fn test_term_wrapped() {
+        let small = parse_prog("λx.(x y);").unwrap().pop().unwrap();
+        assert_eq!(
+            print::term_wrapped(small.term(), 20),
+            print::term(small.term())
+        );
+        assert!(!print::term_wrapped(small.term(), 20).contains('\n'));
+
+        let wide = parse_prog("λf.λx.(f (f (f (f (f (f (f (f x))))))));")
+            .unwrap()
+            .pop()
+            .unwrap();
+        let wrapped = print::term_wrapped(wide.term(), 20);
+        assert!(wrapped.lines().count() > 1);
+        assert_eq!(
+            strip_color(&print::term_wrapped(wide.term(), 30)),
+            "λf.λx.\n  (f\n    (f\n      (f\n        (f (f (f (f (f x))))))))"
+        );
+    }
+
+    #[test]
+    fn test_env_dependencies() {
+        let mut env = HashMap::new();
+        for expr in parse_prog("a = λx.x; b = a a; c = λy.(b y z);").unwrap() {
+            eval_expr(
+                &expr,
+                &mut env,
+                &Config::default(),
+                PRINT_NONE,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        }
+        let deps = env_dependencies(&env);
+        assert!(deps["a"].is_empty());
+        assert_eq!(deps["b"], ["a".to_string()].into());
+        assert_eq!(deps["c"], ["b".to_string()].into());
+    }
+
+    #[test]
+    fn test_eval_lazy_sharing() {
+        let env = HashMap::new();
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        // The argument is used twice, but only reduced once
+        let dup = term("(λx.λf.(f x x)) ((λy.y) z);");
+        let (result, steps) = eval_lazy(&dup, &env, DEFAULT_STEP_LIMIT).unwrap();
+        assert_eq!(steps, 2);
+        assert!(alpha_eq(&result, &term("λf.(f z z);")));
+        assert_eq!(
+            result,
+            reduce_to_normal_form(&dup, &env, &Config::default(), PRINT_NONE)
+        );
+        // Unused divergent arguments are never evaluated
+        let omega = term("(λx.λy.y) ((λx.(x x)) (λx.(x x)));");
+        let (result, _) = eval_lazy(&omega, &env, DEFAULT_STEP_LIMIT).unwrap();
+        assert_eq!(result, term("λy.y;"));
+        assert!(eval_lazy(&term("(λx.(x x)) (λx.(x x));"), &env, 100).is_none());
+    }
+
+    #[test]
+    fn test_eval_lazy_env() {
+        let mut env = HashMap::new();
+        let input = "Succ = λn.λf.λx.(f ((n f) x)); Two = λf.λx.(f (f x)); Four = Succ (Succ Two);";
+        for expr in parse_prog(input).unwrap() {
+            eval_expr(
+                &expr,
+                &mut env,
+                &Config::default(),
+                PRINT_NONE,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        }
+        let four = Term::Variable("Four".to_string());
+        let (result, _) = eval_lazy(&four, &env, DEFAULT_STEP_LIMIT).unwrap();
+        assert!(alpha_eq(
+            &result,
+            parse_prog("λf.λx.(f (f (f (f x))));")
+                .unwrap()
+                .pop()
+                .unwrap()
+                .term()
+        ));
+    }
+
+    #[test]
+    fn test_strategy_flag() {
+        for (flag, strategy) in [
+            ("normal", Strategy::Normal),
+            ("applicative", Strategy::Applicative),
+            ("cbv", Strategy::Cbv),
+            ("cbn", Strategy::Cbn),
+        ] {
+            let config = Config::try_parse_from(["lambda", "--strategy", flag]).unwrap();
+            assert_eq!(config.strategy, strategy);
+        }
+        assert_eq!(Config::default().strategy, Strategy::Normal);
+        let err = Config::try_parse_from(["lambda", "--strategy", "eager"]).unwrap_err();
+        assert!(err.to_string().contains("normal, applicative, cbv, cbn"));
+    }
+
+    #[test]
+    fn test_strategy_divergence() {
+        let env = HashMap::new();
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        // The discarded argument diverges, so only lazy strategies terminate
+        let discard = term("(λx.y) ((λx.(x x)) (λx.(x x)));");
+        for (strategy, terminates) in [
+            (Strategy::Normal, true),
+            (Strategy::Applicative, false),
+            (Strategy::Cbv, false),
+            (Strategy::Cbn, true),
+        ] {
+            let result = reduce_bounded(&discard, &env, strategy, 100);
+            assert_eq!(result.is_ok(), terminates, "{:?}", strategy);
+        }
+        // Weak strategies do not reduce under abstractions
+        let under = term("λz.((λx.x) z);");
+        assert_eq!(
+            reduce_bounded(&under, &env, Strategy::Cbn, 100).unwrap(),
+            under
+        );
+        assert_eq!(
+            reduce_bounded(&under, &env, Strategy::Applicative, 100).unwrap(),
+            term("λz.z;")
+        );
+    }
+
+    #[test]
+    fn test_might_diverge() {
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        assert!(might_diverge(&term("(λx.(x x)) (λx.(x x));")));
+        assert!(might_diverge(&term("λy.((λx.(y (x x))) z);")));
+        assert!(!might_diverge(&term("(λx.x) y;")));
+        // Self-application of an unapplied or shadowed binder is not flagged
+        assert!(!might_diverge(&term("λx.(x x);")));
+        assert!(!might_diverge(&term("(λx.λx.x) (λx.(x x));")));
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut env = HashMap::new();
+        let input = "x = λa.a; x = λb.b; x (y z);";
+        let warnings = eval_prog(input.to_string(), &mut env, &Config::default(), PRINT_NONE);
+        assert_eq!(
+            warnings,
+            [
+                Warning::new(WarningKind::Shadowing, "`x` is redefined"),
+                Warning::new(WarningKind::UnboundVariable, "`y` is not defined"),
+                Warning::new(WarningKind::UnboundVariable, "`z` is not defined"),
+            ]
+        );
+        let input = "(λx.(x x)) (λy.y);";
+        let warnings = eval_prog(
+            input.to_string(),
+            &mut HashMap::new(),
+            &Config::default(),
+            PRINT_NONE,
+        );
+        assert_eq!(
+            warnings.iter().map(|w| w.kind).collect::<Vec<_>>(),
+            [WarningKind::MightDiverge]
+        );
+        // Terms using numeral literals above the cap are not reduced, also through a definition
+        let input = format!("n = {}; (λx.x) n;", NUMERAL_CAP + 1);
+        let outcome = run_prog(input, &mut HashMap::new(), &Config::default(), PRINT_NONE);
+        let message = format!(
+            "numeral literal `{}` is larger than {} and is not reduced",
+            NUMERAL_CAP + 1,
+            NUMERAL_CAP
+        );
+        assert_eq!(
+            outcome.warnings,
+            [Warning::new(WarningKind::NumeralCap, message)]
+        );
+        assert_eq!(outcome.value, Some(parse_term_str("(λx.x) n").unwrap()));
+        let config = Config {
+            step_limit: Some(1),
+            ..Config::default()
+        };
+        let input = format!("{};", NUMERAL_CAP);
+        let warnings = eval_prog(input, &mut HashMap::new(), &config, PRINT_NONE);
+        assert!(warnings.iter().all(|w| w.kind != WarningKind::NumeralCap));
+    }
+
+    #[test]
+    fn test_term_abbreviated() {
+        let ten = parse_prog("λf.λx.(f (f (f (f (f (f (f (f (f (f x))))))))));")
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(
+            strip_color(&print::term_abbreviated(ten.term())),
+            "λf.λx.(f^10 x)"
+        );
+        // Short runs and non-repetitive terms are printed as usual
+        for input in ["λf.λx.(f (f x));", "λx.(x y);", "(f (g (f x)));"] {
+            let t = parse_prog(input).unwrap().pop().unwrap();
+            assert_eq!(print::term_abbreviated(t.term()), print::term(t.term()));
+        }
+        let config = Config {
+            abbreviate: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            print::result(ten.term(), &HashMap::new(), &config),
+            print::term_abbreviated(ten.term())
+        );
+        assert_eq!(
+            print::result(ten.term(), &HashMap::new(), &Config::default()),
+            print::term(ten.term())
+        );
+    }
+
+    #[test]
+    fn test_eval_flag() {
+        let config = Config::try_parse_from(["lambda", "-e", "(λx.x) y"]).unwrap();
+        assert_eq!(config.eval.as_deref(), Some("(λx.x) y"));
+        let program = config.eval.clone().unwrap();
+        eval_prog(program, &mut HashMap::new(), &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["y"]);
+        assert!(Config::try_parse_from(["lambda", "-e", "x", "prog.lc"]).is_err());
+    }
+
+    #[test]
+    fn test_term_builders() {
+        let prog = parse_prog("λx.(x y); id = λx.x;").unwrap();
+        assert_eq!(
+            prog,
+            [
+                Expr::Term(abs("x", app(var("x"), var("y")))),
+                assign("id", abs("x", var("x"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_beta_reduce_normal_order() {
+        let env = HashMap::new();
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        let steps = |input: &str| {
+            let mut t = term(input);
+            let mut trace = vec![print::term(&t)];
+            loop {
+                let next = beta_reduce(&t, &env, HashSet::new());
+                if next == t {
+                    return trace
+                        .into_iter()
+                        .map(|s| strip_color(&s))
+                        .collect::<Vec<_>>();
+                }
+                trace.push(print::term(&next));
+                t = next;
+            }
+        };
+        // The outermost redex is contracted first, discarding the argument unreduced
+        assert_eq!(
+            steps("(λx.λy.y) ((λz.z) a);"),
+            ["(λx.λy.y (λz.z a))", "λy.y"]
+        );
+        // The function is reduced before the argument
+        assert_eq!(
+            steps("((λx.x) (λy.y)) ((λz.z) a);"),
+            ["((λx.x λy.y) (λz.z a))", "(λy.y (λz.z a))", "(λz.z a)", "a"]
+        );
+        assert_eq!(
+            steps("(f ((λx.x) a)) ((λy.y) b);"),
+            ["((f (λx.x a)) (λy.y b))", "((f a) (λy.y b))", "((f a) b)"]
+        );
+        // Redexes under abstractions are reduced outermost first
+        assert_eq!(
+            steps("λw.((λx.x) ((λy.y) w));"),
+            ["λw.(λx.x (λy.y w))", "λw.(λy.y w)", "λw.w"]
+        );
+    }
+
+    #[test]
+    fn test_quiet_flag() {
+        let program = "Id = λx.x; Id (Id y);";
+        let config = Config::try_parse_from(["lambda", "--quiet"]).unwrap();
+        assert!(config.quiet);
+        let warnings = eval_prog(
+            program.to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["y"]);
+        // The unbound `y` is still collected, but not reported as it is by default
+        assert_eq!(warnings[0].kind, WarningKind::UnboundVariable);
+        assert!(reported(&warnings, &config).is_empty());
+        assert_eq!(reported(&warnings, &Config::default()), warnings);
+        // Verbose mode echoes the source and every step, which quiet mode omits
+        let config = Config::try_parse_from(["lambda", "--verbose"]).unwrap();
+        eval_prog(
+            program.to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert!(captured().len() > 1);
+        assert!(Config::try_parse_from(["lambda", "--quiet", "--verbose"]).is_err());
+    }
+
+    #[test]
+    fn test_numeral_literals() {
+        let mut env = HashMap::new();
+        let input = "True = λt.λf.t; False = λt.λf.f; IsZero = λn.((n λx.False) True);";
+        for expr in parse_prog(input).unwrap() {
+            eval_expr(
+                &expr,
+                &mut env,
+                &Config::default(),
+                PRINT_NONE,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        }
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        // Large literals are unfolded lazily, so this only takes a handful of steps
+        let start = std::time::Instant::now();
+        let result = reduce_bounded(&term("IsZero 1000;"), &env, Strategy::Normal, 50).unwrap();
+        assert!(start.elapsed().as_millis() < 1000);
+        assert!(alpha_eq(&result, &term("λt.λf.f;")));
+        let result = reduce_bounded(&term("IsZero 0;"), &env, Strategy::Normal, 50).unwrap();
+        assert!(alpha_eq(&result, &term("λt.λf.t;")));
+        // Fully evaluated literals decode back to their value
+        let result = reduce_bounded(&term("100;"), &env, Strategy::Normal, DEFAULT_STEP_LIMIT);
+        assert_eq!(from_church_numeral(&result.unwrap()), Some(100));
+        // Definitions take precedence over literals
+        env.insert("3".to_string(), term("λf.λx.x;"));
+        let result = reduce_bounded(&term("3;"), &env, Strategy::Normal, 50).unwrap();
+        assert_eq!(from_church_numeral(&result), Some(0));
+    }
+
+    #[test]
+    fn test_term_diff() {
+        let term = |s: &str| parse_prog(s).unwrap().pop().unwrap().term().clone();
+        let a = term("λx.(x (y z));");
+        let same = print::term_diff(&a, &a);
+        assert_eq!(same, format!("{}\n{}", print::term(&a), print::term(&a)));
+        assert!(!same.contains("\x1b[1;3"));
+
+        let b = term("λx.(x (λw.w z));");
+        let diff = print::term_diff(&a, &b);
+        let lines = diff.lines().collect::<Vec<_>>();
+        assert!(lines[0].contains("\x1b[1;31my\x1b[0m"));
+        assert!(lines[1].contains("\x1b[1;32mλw.w\x1b[0m"));
+        // Only the changed subtree is highlighted
+        assert!(!lines[0].contains("\x1b[1;31mz"));
+        assert_eq!(strip_color(lines[0]), strip_color(&print::term(&a)));
+        assert_eq!(strip_color(lines[1]), strip_color(&print::term(&b)));
+    }
+
+    #[test]
+    fn test_parse_term_str() {
+        assert_eq!(
+            parse_term_str("λx.(x y)").unwrap(),
+            abs("x", app(var("x"), var("y")))
+        );
+        assert_eq!(
+            parse_term_str("f a b;").unwrap(),
+            app(app(var("f"), var("a")), var("b"))
+        );
+        assert!(parse_term_str("λx.x )").is_err());
+        assert!(parse_term_str("x; y").is_err());
+        assert!(parse_term_str("x = y").is_err());
+        assert!(parse_term_str("").is_err());
+    }
+
+    #[test]
+    fn test_strict_app_arity() {
+        assert!(check_app_arity("f a b;").is_err());
+        assert!(check_app_arity("λx.(x y z);").is_err());
+        assert!(check_app_arity("((f a) b); λx.(x y);").is_ok());
+        // The default mode accepts and parses the juxtaposed application
+        let config = Config::try_parse_from(["lambda", "--strict-app-arity"]).unwrap();
+        eval_prog(
+            "f a b;".to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert!(captured().is_empty());
+        let config = Config::default();
+        eval_prog(
+            "f a b;".to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["((f a) b)"]);
+        // The prelude juxtaposes terms, but is loaded regardless of the flag
+        let config = Config::try_parse_from(["lambda", "--strict-app-arity"]).unwrap();
+        let mut env = HashMap::new();
+        let outcome = run_prog(
+            PRELUDE.to_string(),
+            &mut env,
+            &prelude_config(&config),
+            PRINT_NONE,
+        );
+        assert!(outcome.errors.is_empty());
+        assert!(env.contains_key("Fib") && env.contains_key("Map"));
+    }
+
+    #[test]
+    fn test_eval_steps() {
+        let env = HashMap::new();
+        let input = parse_term_str("((λx.x) (λy.y)) ((λz.z) a)").unwrap();
+        assert_eq!(eval_steps(&input, 0, &env), input);
+        let mut trace = vec![input.clone()];
+        while let Some(next) = reduce_step(trace.last().unwrap(), &env, Strategy::Normal) {
+            trace.push(next);
+        }
+        assert_eq!(trace.len(), 4);
+        for (n, expected) in trace.iter().enumerate() {
+            assert_eq!(&eval_steps(&input, n, &env), expected);
+        }
+        // Stops at normal form when given more steps than needed
+        assert_eq!(eval_steps(&input, 100, &env), var("a"));
+    }
+
+    #[test]
+    fn test_mutual_recursion() {
+        let mut env = HashMap::new();
+        let input = "
+            True = λt.λf.t; False = λt.λf.f;
+            IsZero = λn.((n λx.False) True);
+            Pred = λn.λf.λx.(n (λg.λh.(h (g f))) (λu.x) (λu.u));
+            isEven = λn.(IsZero n True (isOdd (Pred n)))
+            and isOdd = λn.(IsZero n False (isEven (Pred n)));
+        ";
+        let prog = parse_prog(input).unwrap();
+        assert_eq!(prog.len(), 6);
+        for expr in &prog {
+            eval_expr(
+                expr,
+                &mut env,
+                &Config::default(),
+                PRINT_NONE,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        }
+        // The group is compiled into a fixpoint without references to its own names
+        assert!(!free_vars(&env["isEven"]).contains("isOdd"));
+        assert!(!free_vars(&env["isOdd"]).contains("isEven"));
+        let eval = |s: &str| {
+            reduce_bounded(
+                &parse_term_str(s).unwrap(),
+                &env,
+                Strategy::Normal,
+                DEFAULT_STEP_LIMIT,
+            )
+        };
+        assert!(alpha_eq(
+            &eval("isEven 4").unwrap(),
+            &parse_term_str("λt.λf.t").unwrap()
+        ));
+        assert!(alpha_eq(
+            &eval("isOdd 4").unwrap(),
+            &parse_term_str("λt.λf.f").unwrap()
+        ));
+        assert!(alpha_eq(
+            &eval("isOdd 3").unwrap(),
+            &parse_term_str("λt.λf.t").unwrap()
+        ));
+        // `and` is reserved, so it cannot be used as a variable
+        assert!(parse_term_str("and").is_err());
+        assert!(parse_term_str("andy").is_ok());
+    }
+
+    #[test]
+    fn test_to_combinator_string() {
+        for (input, expected) in [
+            ("λx.x", "I"),
+            ("λx.λy.x", "S (K K) I"),
+            ("λf.λx.(f x)", "S (S (K S) (S (K K) I)) (K I)"),
+            ("λx.(y x)", "S (K y) I"),
+        ] {
+            let term = parse_term_str(input).unwrap();
+            let ski = to_combinator_string(&term);
+            assert_eq!(ski, expected);
+            // The output parses back into the same combinator term
+            assert_eq!(parse_term_str(&ski).unwrap(), to_ski(&term));
+        }
+        // The combinator term behaves like the original abstraction
+        let mut env = HashMap::new();
+        for expr in parse_prog("S = λx.λy.λz.((x z) (y z)); K = λx.λy.x; I = λx.x;").unwrap()
+        {
+            eval_expr(
+                &expr,
+                &mut env,
+                &Config::default(),
+                PRINT_NONE,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        }
+        let applied = parse_term_str("S (K K) I a b").unwrap();
+        let result = reduce_bounded(&applied, &env, Strategy::Normal, DEFAULT_STEP_LIMIT);
+        assert_eq!(result.unwrap(), var("a"));
+    }
+
+    #[test]
+    fn test_undo() {
+        let mut env = HashMap::new();
+        let mut undo = UndoStack::new();
+        let config = Config::default();
+        for line in ["a = λx.x;", "a;", "x = a a;", "x = a;"] {
+            let before = env.clone();
+            eval_prog(line.to_string(), &mut env, &config, PRINT_NONE);
+            undo.record(before, &env);
+        }
+        assert!(undo.undo(&mut env));
+        assert_eq!(env["x"], app(var("a"), var("a")));
+        assert!(undo.undo(&mut env));
+        assert!(!env.contains_key("x"));
+        assert!(env.contains_key("a"));
+        // Evaluating `a;` did not change the environment, so the next undo removes `a`
+        assert!(undo.undo(&mut env));
+        assert!(env.is_empty());
+        assert!(!undo.undo(&mut env));
+    }
+
+    #[test]
+    fn test_preprocess() {
+        let lf = "Id = λx.x; -- identity\nK = λx.λy.x;\n\nK Id z;\n";
+        let crlf = "Id = λx.x; -- identity\r\nK = λx.λy.x;  \r\n\rK Id z;\r";
+        assert_eq!(preprocess(lf), preprocess(crlf));
+        assert_eq!(
+            preprocess(crlf),
+            "Id = λx.x; -- identity\nK = λx.λy.x;\n\nK Id z;"
+        );
+        assert_eq!(
+            parse_prog(&preprocess(crlf)).unwrap(),
+            parse_prog(lf).unwrap()
+        );
+        assert_eq!(parse_prog(lf).unwrap().len(), 3);
+        // A comment on the last line does not need a trailing newline
+        assert_eq!(
+            parse_prog(&preprocess("x -- comment\r\n")).unwrap(),
+            [Expr::Term(var("x"))]
+        );
+    }
+
+    #[test]
+    fn test_duplication_report() {
+        let term = |s: &str| parse_term_str(s).unwrap();
+        let big = "((λa.λb.(a b)) (λc.c))";
+        assert_eq!(duplication_report(&term(&format!("(λx.(x x)) {}", big))), 1);
+        assert_eq!(duplication_report(&term(&format!("(λx.x) {}", big))), 0);
+        assert_eq!(
+            duplication_report(&term(&format!("(λx.(x x x)) {}", big))),
+            2
+        );
+        // Discarding an argument copies nothing
+        assert_eq!(duplication_report(&term(&format!("(λx.y) {}", big))), 0);
+    }
+
+    #[test]
+    fn test_print_styles() {
+        let t = parse_term_str("\\x -> \\y -> (x (λz.z) y)").unwrap();
+        assert_eq!(t, parse_term_str("λx.λy.(x (λz.z) y)").unwrap());
+        let expected = [
+            (Style::Unicode, "λx.λy.((x λz.z) y)"),
+            (Style::Ascii, "\\x.\\y.((x \\z.z) y)"),
+            (Style::Haskell, "\\x -> \\y -> ((x \\z -> z) y)"),
+        ];
+        for (style, printed) in expected {
+            let output = strip_color(&print::term_styled(&t, style));
+            assert_eq!(output, printed);
+            // Every style parses back into the same term
+            assert_eq!(parse_term_str(&output).unwrap(), t);
+        }
+        let config = Config::try_parse_from(["lambda", "--style", "haskell"]).unwrap();
+        assert_eq!(
+            strip_color(&print::result(&t, &HashMap::new(), &config)),
+            expected[2].1
+        );
+    }
+
+    #[test]
+    fn test_explain_labels_rules() {
+        let mut env = HashMap::new();
+        env.insert("I".to_string(), parse_term_str("λx.x").unwrap());
+        let term = parse_term_str("I y").unwrap();
+        let (next, rules) = reduce_step_explained(&term, &env, Strategy::Normal).unwrap();
+        assert_eq!(next, var("y"));
+        assert_eq!(
+            rules,
+            [
+                Explanation::Delta("I".to_string()),
+                Explanation::Beta {
+                    param: "x".to_string(),
+                    body: var("x"),
+                    arg: var("y")
+                }
+            ]
+        );
+        let config = Config::try_parse_from(["lambda", "--explain"]).unwrap();
+        reduce_to_normal_form(&term, &env, &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["δ: unfold `I`", "β: (λx.x y) → x[x:=y]", "y"]);
+    }
+
+    #[test]
+    fn test_infix_operators() {
+        let prog = parse_prog("infixl 6 + = add; 2 + 3;").unwrap();
+        assert_eq!(prog, [Expr::Term(app(app(var("add"), var("2")), var("3")))]);
+        // Juxtaposition binds tighter, and precedence and associativity are respected
+        let prog = parse_prog(
+            "infixl 6 + = add; infixl 7 * = mul; infixr 5 : = cons; f a + b * c + d; a : b : c;",
+        )
+        .unwrap();
+        let op = |f: &str, a: Term, b: Term| app(app(var(f), a), b);
+        let sum = op(
+            "add",
+            op(
+                "add",
+                app(var("f"), var("a")),
+                op("mul", var("b"), var("c")),
+            ),
+            var("d"),
+        );
+        let list = op("cons", var("a"), op("cons", var("b"), var("c")));
+        assert_eq!(prog, [Expr::Term(sum), Expr::Term(list)]);
+        // Operators must be declared before use
+        assert!(parse_prog("a + b;").is_err());
+        assert!(parse_term_str("a + b").is_err());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let long = "x".repeat(200);
+        let terms = [
+            var("x"),
+            var(&long),
+            abs("λ'", var("y")),
+            app(var("f"), var("x")),
+            parse_term_str("λf.λx.(f (f (f x)))").unwrap(),
+        ];
+        for term in terms {
+            assert_eq!(from_bytes(&to_bytes(&term)).unwrap(), term);
+        }
+        assert_eq!(
+            to_bytes(&app(var("f"), var("x"))),
+            [2, 0, 1, b'f', 0, 1, b'x']
+        );
+        let bytes = to_bytes(&abs("x", var("x")));
+        assert!(matches!(
+            from_bytes(&bytes[..3]),
+            Err(Error::Decode(DecodeError::UnexpectedEnd))
+        ));
+        assert!(matches!(
+            from_bytes(&[7]),
+            Err(Error::Decode(DecodeError::InvalidTag(7)))
+        ));
+        assert!(matches!(
+            from_bytes(&[bytes, vec![0]].concat()),
+            Err(Error::Decode(DecodeError::TrailingBytes(1)))
+        ));
+        // Lengths that do not fit in a usize are rejected instead of truncated
+        let overlong = [&[0][..], &[0xff; 10], &[0x01]].concat();
+        assert!(matches!(
+            from_bytes(&overlong),
+            Err(Error::Decode(DecodeError::LengthOverflow))
+        ));
+        // Deeply nested terms do not overflow the stack
+        let depth = 50_000;
+        let deep = [[1, 1, b'x'].repeat(depth), vec![0, 1, b'x']].concat();
+        let mut term = Box::new(from_bytes(&deep).unwrap());
+        let mut params = 0;
+        // Dismantle the term iteratively to avoid overflowing on drop
+        while let Term::Abstraction(_, body) = *term {
+            params += 1;
+            term = body;
+        }
+        assert_eq!(params, depth);
+    }
+
+    #[test]
+    fn test_selective_import() {
+        let path = std::env::temp_dir().join(format!("prelude-{}.lc", std::process::id()));
+        std::fs::write(
+            &path,
+            "Id = λx.x;\nConst = λx.λy.(Id x);\nFlip = λf.λa.λb.(f b a);",
+        )
+        .unwrap();
+        let program = format!("import \"{}\" (Const, Flip); Const a b;", path.display());
+        let mut env = HashMap::new();
+        let warnings = eval_prog(program, &mut env, &Config::default(), PRINT_CAPTURE);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(captured(), ["a"]);
+        assert!(warnings.iter().all(|w| w.kind != WarningKind::Shadowing));
+        let mut names = env.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["Const", "Flip"]);
+        // The hidden helper is inlined into the definitions that use it
+        assert_eq!(env["Const"], parse_term_str("λx.λy.(λx.x x)").unwrap());
+        let warnings = eval_prog(
+            "Id c".to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["(Id c)"]);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .all(|w| w.kind == WarningKind::UnboundVariable));
+    }
+
+    #[test]
+    fn test_eval_normal_form_unchanged() {
+        let mut env = HashMap::new();
+        env.insert("x".to_string(), parse_term_str("λa.(a a)").unwrap());
+        env.insert("K".to_string(), parse_term_str("λa.λb.a").unwrap());
+        // `x` is bound, so the term has no free env references
+        let term = parse_term_str("λx.(x y)").unwrap();
+        assert!(is_normal_form(&term, Conversion::Beta));
+        assert!(!is_normal_form(
+            &parse_term_str("λz.(λx.x z)").unwrap(),
+            Conversion::Beta
+        ));
+        let config = Config::try_parse_from(["lambda", "--verbose"]).unwrap();
+        let mut warnings = Vec::new();
+        let result = eval_expr(
+            &Expr::Term(term.clone()),
+            &mut env,
+            &config,
+            PRINT_CAPTURE,
+            &mut warnings,
+        )
+        .unwrap();
+        assert_eq!(result, term);
+        // Only the echoed term is printed, no reduction steps
+        assert_eq!(captured(), ["λx.(x y)"]);
+        // Bound names are not inlined when a term does need reducing
+        assert_eq!(
+            inline_vars(&parse_term_str("λx.(K x)").unwrap(), &env),
+            parse_term_str("λx.(λa.λb.a x)").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_color_choice() {
+        let config = Config::try_parse_from(["lambda", "--color", "always"]).unwrap();
+        assert!(config.color.enabled(false));
+        let config = Config::try_parse_from(["lambda", "--color", "never"]).unwrap();
+        assert!(!config.color.enabled(true));
+        let config = Config::try_parse_from(["lambda"]).unwrap();
+        assert_eq!(config.color, ColorChoice::Auto);
+        assert!(config.color.enabled(true) && !config.color.enabled(false));
+        let printed = print::term(&abs("x", var("x")));
+        assert!(print::paint(&printed, true).contains('\x1b'));
+        assert_eq!(print::paint(&printed, false), "λx.x");
+    }
+
+    #[test]
+    fn test_macro_expansion() {
+        let prog = parse_prog("macro twice(f) = λx.(f (f x)); twice(g);").unwrap();
+        assert_eq!(prog, [Expr::Term(parse_term_str("λx.(g (g x))").unwrap())]);
+        // Arguments are substituted simultaneously and without capture
+        let prog =
+            parse_prog("macro swap(a, b) = (b a); swap(b, x); macro k(y) = λx.y; k(x);").unwrap();
+        let k = Term::Abstraction("x'".to_string(), Box::new(var("x")));
+        assert_eq!(prog, [Expr::Term(app(var("x"), var("b"))), Expr::Term(k)]);
+        // Names that are not macros, or separated by whitespace, are applications
+        assert_eq!(
+            parse_prog("f(x);").unwrap(),
+            [Expr::Term(app(var("f"), var("x")))]
+        );
+        let prog = parse_prog("macro twice(f) = λx.(f (f x)); twice (g);").unwrap();
+        assert_eq!(prog, [Expr::Term(app(var("twice"), var("g")))]);
+        assert!(parse_prog("macro twice(f) = λx.(f (f x)); twice(f, g);").is_err());
+        assert!(parse_prog("f(a, b, c);").is_err());
+    }
+
+    #[test]
+    fn test_contract_skips_unused_argument() {
+        // A large argument where the free `z` would force renaming if it were substituted
+        let mut big = var("z");
+        for _ in 0..16 {
+            big = app(big.clone(), big);
+        }
+        let env = HashMap::new();
+        let discard = app(abs("x", var("y")), big.clone());
+        let (next, stats) = collect(|| reduce_step(&discard, &env, Strategy::Normal));
+        assert_eq!(next, Some(var("y")));
+        assert_eq!(stats, Stats::default());
+        let discard = app(abs("x", abs("z", var("y"))), big.clone());
+        let (next, stats) = collect(|| reduce_step(&discard, &env, Strategy::Normal));
+        assert_eq!(next, Some(abs("z", var("y"))));
+        assert_eq!(stats, Stats::default());
+        let identity = app(abs("x", var("x")), big.clone());
+        let (next, stats) = collect(|| reduce_step(&identity, &env, Strategy::Cbv));
+        assert_eq!(next, Some(big.clone()));
+        assert_eq!(stats, Stats::default());
+        // Substituting the same argument scans it for free variables and renames `z`
+        let used = app(abs("x", abs("z", var("x"))), big);
+        let (_, stats) = collect(|| reduce_step(&used, &env, Strategy::Normal));
+        assert_eq!((stats.free_var_scans, stats.renames), (1, 1));
+    }
+
+    #[test]
+    fn test_walk_and_map() {
+        let term = parse_term_str("λx.((x y) λz.z)").unwrap();
+        let mut visited = Vec::new();
+        walk(&term, &mut |t| visited.push(t.clone()));
+        assert_eq!(
+            visited,
+            [
+                term.clone(),
+                app(app(var("x"), var("y")), abs("z", var("z"))),
+                app(var("x"), var("y")),
+                var("x"),
+                var("y"),
+                abs("z", var("z")),
+                var("z"),
+            ]
+        );
+        // Replace every variable `y` and count the nodes visited by `map`
+        let mut nodes = 0;
+        let mapped = map(&term, &mut |t| {
+            nodes += 1;
+            match t {
+                Term::Variable(v) if v == "y" => var("w"),
+                t => t,
+            }
+        });
+        assert_eq!(mapped, parse_term_str("λx.((x w) λz.z)").unwrap());
+        assert_eq!(nodes, visited.len());
+    }
+
+    #[test]
+    fn test_reduction_skips_normal_functions() {
+        let mut env = HashMap::new();
+        eval_prog(
+            PRELUDE.to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+        );
+        // Steps that skip functions marked normal take the same path as fresh steps
+        let term = parse_term_str("x 3 (Add 1 2) (Mul 2 (Succ 1))").unwrap();
+        let mut trace = vec![term.clone()];
+        while let Some(next) = reduce_step(trace.last().unwrap(), &env, Strategy::Normal) {
+            trace.push(next);
+        }
+        for (n, expected) in trace.iter().enumerate() {
+            assert_eq!(&eval_steps(&term, n, &env), expected);
+        }
+        let normal = reduce_bounded(&term, &env, Strategy::Normal, DEFAULT_STEP_LIMIT);
+        assert_eq!(normal.ok().as_ref(), trace.last());
+    }
+
+    #[test]
+    fn test_use_aliases() {
+        let program = "id = λx.x; I = λy.y; K = λa.λb.a; id;".to_string();
+        let config = Config::try_parse_from(["lambda", "--use-aliases"]).unwrap();
+        // Both `id` and `I` match, the shortest name is preferred
+        eval_prog(program.clone(), &mut HashMap::new(), &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["I"]);
+        eval_prog(
+            program,
+            &mut HashMap::new(),
+            &Config::default(),
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["λx.x"]);
+        let program = "id = λx.x; id;".to_string();
+        eval_prog(program, &mut HashMap::new(), &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["id"]);
+        // Subterms are aliased too
+        let program = "K = λa.λb.a; λz.(z λp.λq.p);".to_string();
+        eval_prog(program, &mut HashMap::new(), &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["λz.(z K)"]);
+    }
+
+    #[test]
+    fn test_embedded_prelude() {
+        let config = Config::default();
+        let prelude = prelude_source(&config).unwrap().unwrap();
+        assert_eq!(prelude, PRELUDE);
+        let mut env = HashMap::new();
+        eval_prog(prelude, &mut env, &config, PRINT_NONE);
+        let succ = parse_term_str("Succ 0").unwrap();
+        let result = eval_expr(
+            &Expr::Term(succ),
+            &mut env,
+            &config,
+            PRINT_NONE,
+            &mut Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(from_church_numeral(&result), Some(1));
+        let config = Config::try_parse_from(["lambda", "--no-prelude"]).unwrap();
+        assert!(prelude_source(&config).is_none());
+        let config =
+            Config::try_parse_from(["lambda", "--prelude-path", "/nonexistent.lc"]).unwrap();
+        assert!(prelude_source(&config).unwrap().is_err());
+    }
+
+    #[test]
+    fn test_max_output_size() {
+        let numeral = to_church_numeral(100);
+        let full = strip_color(&print::term(&parse_term_str("λf.λx.(f x)").unwrap()));
+        assert_eq!(full, "λf.λx.(f x)");
+        let config = Config::try_parse_from(["lambda", "--max-output-size", "20"]).unwrap();
+        let output = strip_color(&print::result(&numeral, &HashMap::new(), &config));
+        let total = strip_color(&print::term(&numeral)).chars().count();
+        let expected = format!("λf.λx.(f (f (f (f (f… ({} characters)", total);
+        assert_eq!(output, expected);
+        // Results within the limit are printed in full
+        let config = Config::try_parse_from(["lambda", "--max-output-size", "11"]).unwrap();
+        let one = parse_term_str("λf.λx.(f x)").unwrap();
+        assert_eq!(
+            strip_color(&print::result(&one, &HashMap::new(), &config)),
+            full
+        );
+    }
+
+    #[test]
+    fn test_error_variants() {
+        let parse = parse_term_str("λx.").unwrap_err();
+        assert!(matches!(parse, Error::Parse(_)));
+        assert!(parse.to_string().contains("expected"));
+        let errors = [
+            (Error::UnboundVar("x".to_string()), "`x` is not defined"),
+            (
+                Error::StepLimit { steps: 10 },
+                "no normal form was reached within 10 steps",
+            ),
+            (Error::NumeralOverflow, "numeral literal is too large"),
+            (
+                Error::NumeralCap(NUMERAL_CAP + 1),
+                &format!(
+                    "numeral literal `{}` is larger than {} and is not reduced",
+                    NUMERAL_CAP + 1,
+                    NUMERAL_CAP
+                ),
+            ),
+            (
+                Error::ImportCycle(vec![
+                    "a.lc".to_string(),
+                    "b.lc".to_string(),
+                    "a.lc".to_string(),
+                ]),
+                "import cycle: a.lc -> b.lc -> a.lc",
+            ),
+            (
+                Error::Decode(DecodeError::InvalidTag(7)),
+                "invalid binary term: invalid term tag 7",
+            ),
+            (
+                Error::Io(std::io::Error::new(std::io::ErrorKind::NotFound, "missing")),
+                "missing",
+            ),
+        ];
+        for (error, message) in errors {
+            assert_eq!(error.to_string(), message);
+        }
+        // Failures of library functions are reported as their variant
+        let omega = parse_term_str("(λx.(x x)) (λx.(x x))").unwrap();
+        let limit = reduce_bounded(&omega, &HashMap::new(), Strategy::Normal, 10);
+        assert!(matches!(limit, Err(Error::StepLimit { steps: 10 })));
+        let overflow = parse_term_str("99999999999999999999999999");
+        assert!(matches!(overflow, Err(Error::NumeralOverflow)));
+        // Imports report cycles and missing names instead of looping or silently skipping
+        let dir = std::env::temp_dir();
+        let a = dir.join(format!("cycle-a-{}.lc", std::process::id()));
+        let b = dir.join(format!("cycle-b-{}.lc", std::process::id()));
+        std::fs::write(&a, format!("import \"{}\"; A = a;", b.display())).unwrap();
+        std::fs::write(&b, format!("import \"{}\"; B = b;", a.display())).unwrap();
+        let mut env = HashMap::new();
+        let program = format!("import \"{}\";", a.display());
+        let outcome = run_prog(program, &mut env, &Config::default(), PRINT_NONE);
+        assert!(matches!(outcome.errors[..], [Error::ImportCycle(_)]));
+        let c = dir.join(format!("names-c-{}.lc", std::process::id()));
+        std::fs::write(&c, "C = c;").unwrap();
+        let program = format!("import \"{}\" (Missing);", c.display());
+        let mut defs = HashMap::new();
+        let missing = parse_prog(&program).unwrap();
+        let missing = eval_expr(
+            &missing[0],
+            &mut defs,
+            &Config::default(),
+            PRINT_NONE,
+            &mut Vec::new(),
+        );
+        std::fs::remove_file(&a).unwrap();
+        std::fs::remove_file(&b).unwrap();
+        std::fs::remove_file(&c).unwrap();
+        assert!(matches!(missing, Err(Error::UnboundVar(name)) if name == "Missing"));
+        assert!(env.is_empty());
+        assert!(defs.is_empty());
+        // Failed assertions are returned as well, and the REPL prints the errors of a line
+        let assertion = parse_prog("assert a == b;").unwrap();
+        let failed = eval_expr(
+            &assertion[0],
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+            &mut Vec::new(),
+        );
+        assert!(matches!(failed, Err(Error::AssertionFailed(_, _))));
+        let outcome = run_prog("λx.;".to_string(), &mut env, &Config::default(), PRINT_NONE);
+        assert_eq!(outcome.errors.len(), 1);
+        assert!(outcome.errors[0].to_string().starts_with(" --> 1:"));
+        assert!(outcome.errors[0].to_string().contains("expected"));
+    }
+
+    #[test]
+    fn test_beta_eta_eq() {
+        let t = |s: &str| parse_term_str(s).unwrap();
+        assert_eq!(beta_eta_eq(&t("λx.(f x)"), &t("f"), 100), Some(true));
+        assert_eq!(beta_eta_eq(&t("λx.(g x)"), &t("f"), 100), Some(false));
+        // Plain β-equality cannot tell these apart
+        assert!(!alpha_eq(&t("λx.(f x)"), &t("f")));
+        // η-redexes that only appear after β-reduction
+        assert_eq!(
+            beta_eta_eq(&t("λy.((λz.z) f y)"), &t("λa.(f a)"), 100),
+            Some(true)
+        );
+        let omega = t("(λx.(x x)) (λx.(x x))");
+        assert_eq!(beta_eta_eq(&omega, &t("f"), 100), None);
+    }
+
+    #[test]
+    fn test_tokenize() {
+        use TokenKind::*;
+        let input = "Id = λx.x; -- identity\n(Id 42)";
+        let tokens = tokenize(input);
+        let expected = [
+            (Ident, "Id"),
+            (Equals, "="),
+            (Lambda, "λ"),
+            (Ident, "x"),
+            (Dot, "."),
+            (Ident, "x"),
+            (Semicolon, ";"),
+            (Comment, "-- identity"),
+            (LParen, "("),
+            (Ident, "Id"),
+            (Number, "42"),
+            (RParen, ")"),
+        ];
+        let actual = tokens
+            .iter()
+            .map(|t| (t.kind, &input[t.span.clone()]))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+        assert_eq!(tokens[2].span, 5..7);
+        // Incomplete input still produces tokens
+        let input = "import \"std (\\f -> f' + #";
+        let kinds = tokenize(input).iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [Keyword, String]);
+        let input = "(\\f -> f' + #";
+        let kinds = tokenize(input).iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [LParen, Lambda, Ident, Arrow, Ident, Operator, Unknown]
+        );
+        // Type abstractions and the later keywords are highlighted too
+        let input = "begin Λa.λx.x end";
+        let kinds = tokenize(input).iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [Keyword, Lambda, Ident, Dot, Lambda, Ident, Dot, Ident, Keyword]
+        );
+    }
+
+    #[test]
+    fn test_explicit_application() {
+        assert_eq!(parse_prog("f @ x").unwrap(), parse_prog("f x").unwrap());
+        assert_eq!(
+            parse_prog("f @ g @ x").unwrap(),
+            parse_prog("(f g) x").unwrap()
+        );
+        assert_eq!(
+            parse_prog("f @ (\\y.y) z").unwrap(),
+            parse_prog("f (\\y.y) z").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_normal_form() {
+        let omega = parse_term_str("(λx.(x x)) (λx.(x x))").unwrap();
+        assert_eq!(normal_form(&omega, 100), None);
+        let term = parse_term_str("(\\x.\\y.x) a b").unwrap();
+        assert_eq!(normal_form(&term, 100), Some(var("a")));
+        assert_eq!(normal_form(&term, 1), None);
+    }
+
+    #[test]
+    fn test_tuples() {
+        assert_eq!(
+            parse_term_str("(a, b)").unwrap(),
+            parse_term_str("λf.(f a b)").unwrap()
+        );
+        // The bound variable does not capture free variables of the components
+        assert_eq!(
+            parse_term_str("(f, g x)").unwrap(),
+            parse_term_str("λf'.(f' f (g x))").unwrap()
+        );
+        let mut env = HashMap::new();
+        eval_prog(
+            PRELUDE.to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+        );
+        let first = parse_term_str("fst (a, b)").unwrap();
+        let second = parse_term_str("snd (a, λx.x)").unwrap();
+        assert_eq!(
+            reduce_bounded(&inline_vars(&first, &env), &env, Strategy::Normal, 100).ok(),
+            Some(var("a"))
+        );
+        // Without whitespace, a name that is not a macro is still applied to the pair
+        let first = parse_term_str("fst(a, b)").unwrap();
+        assert_eq!(
+            reduce_bounded(&inline_vars(&first, &env), &env, Strategy::Normal, 100).ok(),
+            Some(var("a"))
+        );
+        assert_eq!(
+            reduce_bounded(&inline_vars(&second, &env), &env, Strategy::Normal, 100).ok(),
+            Some(abs("x", var("x")))
+        );
+    }
+
+    #[test]
+    fn test_reduce_with_selector() {
+        use std::cell::RefCell;
+        let term = parse_term_str("(λx.y) ((λz.z) w)").unwrap();
+        // Record the redexes contracted by a selector
+        let trace = |select: fn(&[RedexPath]) -> Option<RedexPath>| {
+            let steps = RefCell::new(Vec::new());
+            let result = reduce_with(&term, &|redexes| {
+                let path = select(redexes);
+                steps.borrow_mut().extend(path.clone());
+                path
+            });
+            (result, steps.into_inner())
+        };
+        let rightmost_innermost = |redexes: &[RedexPath]| redexes.last().cloned();
+        let (normal, normal_steps) = trace(normal_order);
+        let (rightmost, rightmost_steps) = trace(rightmost_innermost);
+        assert_eq!(normal, var("y"));
+        assert_eq!(rightmost, var("y"));
+        assert_eq!(normal_steps, vec![vec![]]);
+        assert_eq!(rightmost_steps, vec![vec![Dir::Arg], vec![]]);
+        // Leftmost-innermost picks the argument before the outer redex
+        assert_eq!(trace(applicative_order).1, rightmost_steps);
+        // Under binders and with the selector stopping early
+        let term = parse_term_str("λa.((λb.b) a)").unwrap();
+        assert_eq!(reduce_with(&term, &normal_order), abs("a", var("a")));
+        assert_eq!(reduce_with(&term, &|_| None), term);
+    }
+
+    #[test]
+    fn test_show_steps() {
+        let config = Config::try_parse_from(["lambda", "--show-steps"]).unwrap();
+        let program = "I = (λx.x) (λy.y); I a; (λx.λy.x) a b; λz.z;";
+        let mut env = HashMap::new();
+        eval_prog(program.to_string(), &mut env, &config, PRINT_CAPTURE);
+        assert_eq!(
+            captured(),
+            [
+                "I = λy.y; (1 step)",
+                "a (2 steps)",
+                "a (2 steps)",
+                "λz.z (0 steps)"
+            ]
+        );
+        // Assignments stay unevaluated in the environment
+        assert_eq!(env["I"], parse_term_str("(λx.x) (λy.y)").unwrap());
+        // Assignments without a normal form report the exhausted budget
+        eval_prog(
+            "W = (λx.(x x)) (λx.(x x));".to_string(),
+            &mut env,
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(
+            captured(),
+            [format!(
+                "W = (λx.(x x) λx.(x x)); (no normal form was reached within {} steps)",
+                DEFAULT_STEP_LIMIT
+            )]
+        );
+    }
+
+    #[test]
+    fn test_is_closed() {
+        assert!(is_closed(&parse_term_str("λx.x").unwrap()));
+        assert!(!is_closed(&parse_term_str("λx.(x y)").unwrap()));
+        // Defined names are free until inlined
+        let env = HashMap::from([("I".to_string(), parse_term_str("λx.x").unwrap())]);
+        let term = parse_term_str("I I").unwrap();
+        assert!(!is_closed(&term));
+        assert!(is_closed(&inline_vars(&term, &env)));
+    }
+
+    #[test]
+    fn test_signed_integers() {
+        let mut env = HashMap::new();
+        eval_prog(
+            PRELUDE.to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+        );
+        let eval = |input: &str| {
+            let term = parse_term_str(input).unwrap();
+            let term = reduce_bounded(&inline_vars(&term, &env), &env, Strategy::Normal, 10_000);
+            from_signed_numeral(&term.unwrap())
+        };
+        assert_eq!(eval("negate 3"), Some(-3));
+        assert_eq!(eval("add (negate 3) 5"), Some(2));
+        assert_eq!(eval("add 2 (negate 5)"), Some(-3));
+        assert_eq!(eval("add 3 (negate 3)"), Some(0));
+        assert_eq!(eval("pred (negate 0)"), Some(-1));
+        assert_eq!(eval("succ (negate 1)"), Some(0));
+        assert_eq!(eval("Pred 5"), Some(4));
+        assert_eq!(eval("isNeg (negate 3)"), None);
+        // Naturals stay Church numerals unless an integer is involved
+        let term = parse_term_str("add 1 (succ (pred 1))").unwrap();
+        let term = reduce_bounded(&inline_vars(&term, &env), &env, Strategy::Normal, 10_000);
+        assert_eq!(from_church_numeral(&term.unwrap()), Some(2));
+        assert_eq!(
+            from_signed_numeral(&parse_term_str("λf.λx.x").unwrap()),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn test_debug_ast() {
+        let ast = debug_ast("(λx.x) y").unwrap();
+        assert!(ast.contains("Term("));
+        assert!(ast.contains("Application("));
+        assert!(ast.contains("Abstraction("));
+        assert!(ast.contains("\"y\""));
+        assert!(debug_ast("λ").is_err());
+    }
+
+    #[test]
+    fn test_redefinition() {
+        let mut env = HashMap::new();
+        let config = Config::default();
+        eval_prog(
+            "x = a; x = b; x;".to_string(),
+            &mut env,
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["b"]);
+        assert_eq!(env["x"], var("b"));
+        // References are resolved when evaluated, so they see the latest definition
+        eval_prog(
+            "y = x; x = c; y;".to_string(),
+            &mut env,
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["c"]);
+    }
+
+    #[test]
+    fn test_term_plain() {
+        let term = parse_term_str("λf.λx.(f (True x)) (λy.y) 42").unwrap();
+        let plain = print::term_plain(&term);
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain, strip_color(&print::term(&term)));
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let deep = format!("{}x{}", "(".repeat(100_000), ")".repeat(100_000));
+        let err = check_nesting(&deep, DEFAULT_MAX_NESTING).unwrap_err();
+        assert!(matches!(err, Error::Parse(_)));
+        assert!(err.to_string().contains("nesting too deep"));
+        let warnings = eval_prog(deep, &mut HashMap::new(), &Config::default(), PRINT_CAPTURE);
+        assert!(warnings.is_empty());
+        assert!(captured().is_empty());
+        // Parentheses in comments do not count, and the limit is configurable
+        let nested = format!("{}x{} -- {}", "(".repeat(3), ")".repeat(3), "(".repeat(10));
+        assert!(check_nesting(&nested, 3).is_ok());
+        assert!(check_nesting(&nested, 2).is_err());
+        let config = Config::try_parse_from(["lambda", "--max-nesting", "2"]).unwrap();
+        eval_prog(nested, &mut HashMap::new(), &config, PRINT_CAPTURE);
+        assert!(captured().is_empty());
+        // Abstractions nest until their body ends
+        let deep = format!("{}x;", "λx.".repeat(3000));
+        assert!(check_nesting(&deep, DEFAULT_MAX_NESTING).is_err());
+        eval_prog(deep, &mut HashMap::new(), &Config::default(), PRINT_CAPTURE);
+        assert!(captured().is_empty());
+        assert!(check_nesting("λx.λy.(x (y y)); λz.z", 4).is_ok());
+        assert!(check_nesting("λx.λy.(x (y y)); λz.z", 3).is_err());
+        assert!(check_nesting("(λx.x) (λy.y) λz.z λw.w", 2).is_ok());
+        assert!(check_nesting("begin a = λx.x; λy.(a y) end", 3).is_ok());
+        assert!(check_nesting("begin a = λx.x; λy.(a y) end", 2).is_err());
+        // A printed numeral `n` nests `n + 2` levels deep
+        let numeral = print::term_plain(&to_church_numeral(DEFAULT_MAX_NESTING - 2));
+        assert!(check_nesting(&numeral, DEFAULT_MAX_NESTING).is_ok());
+        let numeral = print::term_plain(&to_church_numeral(DEFAULT_MAX_NESTING - 1));
+        assert!(check_nesting(&numeral, DEFAULT_MAX_NESTING).is_err());
+    }
+
+    #[test]
+    fn test_program_value() {
+        let config = Config::default();
+        let mut env = HashMap::new();
+        let value = run_prog("I = λx.x; I a;".to_string(), &mut env, &config, PRINT_NONE).value;
+        assert_eq!(value, Some(var("a")));
+        // A trailing assignment is the value, without being reduced
+        let value = run_prog(
+            "a; K = (λx.λy.x) I;".to_string(),
+            &mut env,
+            &config,
+            PRINT_NONE,
+        )
+        .value;
+        assert_eq!(value, Some(parse_term_str("(λx.λy.x) I").unwrap()));
+        let value = run_prog("-- nothing".to_string(), &mut env, &config, PRINT_NONE).value;
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_search() {
+        let mut env = HashMap::new();
+        eval_prog(
+            "id = λx.x; I = (λf.f) (λy.y); K = λa.λb.a; L = λx.(M x); M = λx.(L x);".to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+        );
+        let id = parse_term_str("λx.x").unwrap();
+        assert_eq!(search(&id, &env, DEFAULT_STEP_LIMIT), ["I", "id"]);
+        let k = parse_term_str("K").unwrap();
+        assert_eq!(search(&k, &env, DEFAULT_STEP_LIMIT), ["K"]);
+        assert!(search(&var("z"), &env, DEFAULT_STEP_LIMIT).is_empty());
+        assert!(is_recursive("L", &env));
+        assert!(!is_recursive("K", &env));
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let mut cache = ResultCache::new(2);
+        let mut env = HashMap::new();
+        let reductions = std::cell::Cell::new(0);
+        // Evaluate an input, reusing a cached result if there is one
+        let eval = |input: &str, env: &Env, cache: &mut ResultCache| {
+            if let Some((output, _)) = cache.get(input, env) {
+                return output;
+            }
+            reductions.set(reductions.get() + 1);
+            let term = parse_term_str(input).unwrap();
+            let output = print::term_plain(&reduce_to_normal_form(
+                &term,
+                env,
+                &Config::default(),
+                PRINT_NONE,
+            ));
+            cache.insert(input, env, (output.clone(), Vec::new()));
+            output
+        };
+        assert_eq!(eval("(λx.x) a", &env, &mut cache), "a");
+        assert_eq!(eval("  (λx.x) a ", &env, &mut cache), "a");
+        assert_eq!(reductions.get(), 1);
+        // Changing the environment invalidates the cache
+        env.insert("a".to_string(), var("b"));
+        assert_eq!(eval("(λx.x) a", &env, &mut cache), "b");
+        assert_eq!(reductions.get(), 2);
+        // The least recently used entry is evicted when full
+        eval("c", &env, &mut cache);
+        eval("(λx.x) a", &env, &mut cache);
+        eval("d", &env, &mut cache);
+        assert_eq!(reductions.get(), 4);
+        eval("(λx.x) a", &env, &mut cache);
+        assert_eq!(reductions.get(), 4);
+        eval("c", &env, &mut cache);
+        assert_eq!(reductions.get(), 5);
+    }
+
+    #[test]
+    fn test_church_numeral_round_trip() {
+        for n in 0..=10 {
+            assert_eq!(from_church_numeral(&to_church_numeral(n)), Some(n));
+        }
+        assert_eq!(
+            to_church_numeral(2),
+            parse_term_str("λf.λx.(f (f x))").unwrap()
+        );
+        assert_eq!(
+            from_church_numeral(&parse_term_str("λf.λx.(x f)").unwrap()),
+            None
+        );
+        assert_eq!(
+            from_church_numeral(&parse_term_str("λx.λx.x").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_statement_error_isolation() {
+        let config = Config::try_parse_from(["lambda", "--show-steps"]).unwrap();
+        let mut env = HashMap::new();
+        let program = "(λx.x) a;\nb = λ;\nc = (λy.y) d;";
+        let outcome = run_prog(program.to_string(), &mut env, &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["a (1 step)", "c = d; (1 step)"]);
+        assert_eq!(outcome.errors.len(), 1);
+        assert!(outcome.errors[0].to_string().contains("2:6"));
+        assert!(!env.contains_key("b"));
+        // Declarations before an invalid statement are still in effect
+        let program = "infixl 6 + = Plus; a + ; b + e";
+        let outcome = run_prog(
+            program.to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_CAPTURE,
+        );
+        assert_eq!(outcome.errors.len(), 1);
+        assert_eq!(captured(), ["((Plus b) e)"]);
+        // Statements inside a block do not end the statement the block is in
+        let program = "x = begin i = λy.y; i z end; b = λ; x;";
+        let outcome = run_prog(
+            program.to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_CAPTURE,
+        );
+        assert_eq!(outcome.errors.len(), 1);
+        assert!(outcome.errors[0].to_string().contains("1:35"));
+        assert_eq!(captured(), ["z"]);
+        let outcome = run_prog("a; b;".to_string(), &mut env, &config, PRINT_CAPTURE);
+        assert!(outcome.errors.is_empty());
+        captured();
+    }
+
+    #[test]
+    fn test_eager_defs() {
+        let program = "Y = λf.((λx.(f (x x))) (λx.(f (x x)))); \
+            F = Y (λr.λn.n); K = (λx.λy.x) a; F b;";
+        // Definitions are stored unreduced by default, so Y-based ones are usable
+        let mut env = HashMap::new();
+        let config = Config::default();
+        let warnings = eval_prog(program.to_string(), &mut env, &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["b"]);
+        assert_eq!(env["K"], parse_term_str("(λx.λy.x) a").unwrap());
+        assert!(warnings.iter().all(|w| !w.message.contains("unreduced")));
+        // Eager definitions are normalized, falling back to unreduced without a normal form
+        let mut env = HashMap::new();
+        let config = Config::try_parse_from(["lambda", "--eager-defs"]).unwrap();
+        let warnings = eval_prog(program.to_string(), &mut env, &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["b"]);
+        assert_eq!(env["K"], parse_term_str("λy.a").unwrap());
+        assert_eq!(env["F"], parse_term_str("Y (λr.λn.n)").unwrap());
+        let unreduced = |name: &str| {
+            let prefix = format!("`{}` is stored unreduced", name);
+            warnings.iter().any(|w| w.message.starts_with(&prefix))
+        };
+        assert!(unreduced("Y") && unreduced("F"));
+        assert!(!unreduced("K"));
+        // Lazy definitions override eager ones from a project file
+        let options = Config::project_options("eager_defs = true").unwrap();
+        let args = ["lambda", "--lazy-defs"].map(String::from);
+        let config = Config::try_parse_with_project(&options, args).unwrap();
+        assert!(config.lazy_defs && !config.eager_defs);
+        let mut env = HashMap::new();
+        let warnings = eval_prog(program.to_string(), &mut env, &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["b"]);
+        assert_eq!(env["K"], parse_term_str("(λx.λy.x) a").unwrap());
+        assert!(warnings.iter().all(|w| !w.message.contains("unreduced")));
+        assert!(Config::try_parse_from(["lambda", "--lazy-defs", "--eager-defs"]).is_err());
+    }
+
+    #[test]
+    fn test_subterm_normal_forms() {
+        let env = HashMap::from([("I".to_string(), parse_term_str("λa.a").unwrap())]);
+        let term = parse_term_str("(λx.x) (λy.y) z").unwrap();
+        let normal_forms = subterm_normal_forms(&term, &env, Strategy::Normal, 100);
+        assert_eq!(normal_forms.len(), 1);
+        assert_eq!(normal_forms[0].0, parse_term_str("(λx.x) (λy.y)").unwrap());
+        assert_eq!(normal_forms[0].1.as_ref().ok(), Some(&abs("y", var("y"))));
+        // Shared subterms are reported once, and definitions are unfolded
+        let term = parse_term_str("f (I b) (I b) (λw.w)").unwrap();
+        let normal_forms = subterm_normal_forms(&term, &env, Strategy::Normal, 100)
+            .into_iter()
+            .map(|(sub, normal)| (print::term_plain(&sub), print::term_plain(&normal.unwrap())))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            normal_forms,
+            [
+                ("((f (I b)) (I b))".to_string(), "((f b) b)".to_string()),
+                ("(f (I b))".to_string(), "(f b)".to_string()),
+                ("(I b)".to_string(), "b".to_string()),
+            ]
+        );
+        // Definitions shadowed by an enclosing binder are not unfolded
+        let term = parse_term_str("(λI.(I x)) a").unwrap();
+        assert!(subterm_normal_forms(&term, &env, Strategy::Normal, 100).is_empty());
+        let term = parse_term_str("(λI.(I x)) (I x)").unwrap();
+        let normal_forms = subterm_normal_forms(&term, &env, Strategy::Normal, 100);
+        assert_eq!(normal_forms.len(), 1);
+        assert_eq!(normal_forms[0].0, parse_term_str("I x").unwrap());
+        assert_eq!(normal_forms[0].1.as_ref().ok(), Some(&var("x")));
+        let config = Config::try_parse_from(["lambda", "--subterm-normal-forms"]).unwrap();
+        eval_prog(
+            "(λx.x) (λy.y) z".to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["(λx.x λy.y) ↠ λy.y", "z"]);
+    }
+
+    #[test]
+    fn test_repl_eval_timeout() {
+        // Ω never reaches a normal form, so only the time limit stops it
+        let mut env = HashMap::new();
+        let config = Config::try_parse_from(["lambda", "--repl-eval-timeout", "20"]).unwrap();
+        let warnings = eval_prog(
+            "(λx.(x x)) (λx.(x x));".to_string(),
+            &mut env,
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["(λx.(x x) λx.(x x))"]);
+        let timeout = warnings.iter().find(|w| w.kind == WarningKind::Timeout);
+        assert_eq!(
+            timeout.map(|w| w.message.as_str()),
+            Some("evaluation timed out after 20 ms")
+        );
+    }
+
+    #[test]
+    fn test_decode_literals() {
+        let config = Config::try_parse_from(["lambda", "--decode"]).unwrap();
+        let decoded = |src: &str| {
+            let t = parse_term_str(src).unwrap();
+            strip_color(&print::result(&t, &HashMap::new(), &config))
+        };
+        assert_eq!(decoded("λt.λf.t"), "true");
+        assert_eq!(decoded("λtrue.λfalse.false"), "false");
+        assert_eq!(decoded("λf.λx.(f (f x))"), "2");
+        // `λf.λx.x` and `λt.λf.f` are the same term, told apart by their binder names
+        assert_eq!(decoded("λf.λx.x"), "0");
+        assert_eq!(decoded("λt.λf.f"), "false");
+        assert_eq!(decoded("λf.λx.(x f)"), "λf.λx.(x f)");
+        assert_eq!(decoded("λf.((f λt.λf.t) λf.λx.(f (f (f x))))"), "-3");
+        assert_eq!(decoded("λf.((f λt.λf.f) λf.λx.(f x))"), "1");
+        // Signed integers built by the prelude decode as well
+        let mut env = HashMap::new();
+        eval_prog(PRELUDE.to_string(), &mut env, &config, PRINT_NONE);
+        let outcome = run_prog("negate 3;".to_string(), &mut env, &config, PRINT_CAPTURE);
+        assert!(outcome.errors.is_empty());
+        assert_eq!(captured(), ["-3"]);
+        let t = parse_term_str("λt.λf.t").unwrap();
+        let plain = strip_color(&print::result(&t, &HashMap::new(), &Config::default()));
+        assert_eq!(plain, "λt.λf.t");
+    }
+
+    #[test]
+    fn test_trace() {
+        let term = parse_term_str("(λx.λy.x) ((λz.z) a) b").unwrap();
+        let (terms, terminated) = trace(&term, 100);
+        assert!(terminated);
+        assert_eq!(terms[0], term);
+        assert_eq!(terms.last(), Some(&var("a")));
+        for pair in terms.windows(2) {
+            let next = reduce_step(&pair[0], &HashMap::new(), Strategy::Normal);
+            assert_eq!(next.as_ref(), Some(&pair[1]));
+        }
+        // Diverging terms stop at the limit
+        let (terms, terminated) = trace(&parse_term_str("(λx.(x x)) (λx.(x x))").unwrap(), 5);
+        assert!(!terminated);
+        assert_eq!(terms.len(), 6);
+    }
+
+    #[test]
+    fn test_grammar_check() {
+        for (name, result) in grammar_check() {
+            assert!(
+                result.is_ok(),
+                "{} failed to parse: {}",
+                name,
+                result.unwrap_err()
+            );
+        }
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let pattern = parse_pattern("(λx.?b) ?a").unwrap();
+        let redex = parse_term_str("(λy.(f y)) (λz.z)").unwrap();
+        let bindings = matches(&pattern, &redex).unwrap();
+        assert_eq!(bindings.len(), 2);
+        assert_eq!(bindings["b"], parse_term_str("f y").unwrap());
+        assert_eq!(bindings["a"], parse_term_str("λz.z").unwrap());
+        assert_eq!(matches(&pattern, &parse_term_str("f a").unwrap()), None);
+        // Repeated holes capture equal subterms, and bound variables match up to α
+        let pattern = parse_pattern("λx.(x ?a ?a)").unwrap();
+        assert!(matches(&pattern, &parse_term_str("λy.(y b b)").unwrap()).is_some());
+        assert!(matches(&pattern, &parse_term_str("λy.(y b c)").unwrap()).is_none());
+        assert!(matches(&pattern, &parse_term_str("λy.(x b b)").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_canonical_output() {
+        let dir = std::env::temp_dir();
+        let a = dir.join(format!("canonical-a-{}.lc", std::process::id()));
+        let b = dir.join(format!("canonical-b-{}.lc", std::process::id()));
+        std::fs::write(&a, "K = λx.λy.x; K (λp.λq.(q p a));").unwrap();
+        std::fs::write(&b, "K = λu.λv.u; K (λm.λa'.(a' m a));").unwrap();
+        let config = Config::try_parse_from(["lambda", "--canonical"]).unwrap();
+        let mut outputs = Vec::new();
+        for path in [&a, &b] {
+            let source = std::fs::read_to_string(path).unwrap();
+            eval_prog(source, &mut HashMap::new(), &config, PRINT_CAPTURE);
+            outputs.push(captured());
+        }
+        std::fs::remove_file(a).unwrap();
+        std::fs::remove_file(b).unwrap();
+        assert_eq!(outputs[0], outputs[1]);
+        // Bound names avoid the free variables of the result
+        assert_eq!(outputs[0], ["λb.λc.λd.((d c) a)"]);
+    }
+
+    #[test]
+    fn test_show_arity() {
+        let config = Config::try_parse_from(["lambda", "--show-arity"]).unwrap();
+        let shown = |src: &str| {
+            let t = parse_term_str(src).unwrap();
+            strip_color(&print::result(&t, &HashMap::new(), &config))
+        };
+        assert_eq!(shown("λx.λy.x"), "<fn/2> λx.λy.x");
+        assert_eq!(shown("λx.(x λy.y)"), "<fn/1> λx.(x λy.y)");
+        assert_eq!(shown("f λy.y"), "(f λy.y)");
+    }
+
+    #[test]
+    fn test_fresh_var_bounded() {
+        let mut avoid = HashSet::new();
+        for base in ["x", &"long".repeat(20)] {
+            let mut name = base.to_string();
+            for _ in 0..100 {
+                avoid.insert(name.clone());
+                name = fresh_var(&name, &avoid);
+                assert!(!avoid.contains(&name));
+                assert!(name.len() <= base.len().max(8) + 4, "{} is too long", name);
+            }
+        }
+        // Short names are primed first, and numbered names parse as variables
+        assert_eq!(fresh_var("x", &HashSet::from(["x".to_string()])), "x'");
+        assert_eq!(parse_term_str("λx_1.x_1").unwrap(), abs("x_1", var("x_1")));
+    }
+
+    #[test]
+    fn test_interpreter() {
+        let mut interpreter = Interpreter::new(Config::default());
+        interpreter.define("S", "λx.λy.λz.(x z (y z))").unwrap();
+        interpreter.define("K", "λx.λy.x").unwrap();
+        assert_eq!(
+            interpreter.eval("S K K (λa.a)").unwrap(),
+            abs("a", var("a"))
+        );
+        assert!(interpreter.warnings().is_empty());
+        assert!(interpreter.eval("S K (").is_err());
+        // Unbound names are reported as warnings, and reset forgets all definitions
+        interpreter.reset();
+        assert_eq!(
+            interpreter.eval("K a").unwrap(),
+            parse_term_str("K a").unwrap()
+        );
+        assert_eq!(interpreter.warnings().len(), 2);
+        let mut interpreter = Interpreter::new(Config::default());
+        interpreter.load_prelude().unwrap();
+        assert_eq!(
+            interpreter
+                .eval("(Add 1) 2")
+                .map(|t| from_church_numeral(&t))
+                .unwrap(),
+            Some(3)
+        );
+        // Terms without a normal form stop at the step limit
+        let omega = interpreter.eval("(λx.(x x)) (λx.(x x))");
+        let steps = DEFAULT_STEP_LIMIT;
+        assert!(matches!(omega, Err(Error::StepLimit { steps: n }) if n == steps));
+        let config = Config::try_parse_from(["lambda", "--step-limit", "5"]).unwrap();
+        let omega = Interpreter::new(config).eval("(λx.(x x)) (λx.(x x))");
+        assert!(matches!(omega, Err(Error::StepLimit { steps: 5 })));
+    }
+
+    #[test]
+    fn test_stats() {
+        // Substituting `y` under `λy` renames the binder to avoid capturing it
+        let term = parse_term_str("(λx.λy.(x y)) y").unwrap();
+        let (reduced, stats) =
+            collect(|| reduce_bounded(&term, &HashMap::new(), Strategy::Normal, 10));
+        assert_eq!(reduced.unwrap(), parse_term_str("λy'.(y y')").unwrap());
+        assert_eq!(stats.renames, 1);
+        assert!(stats.substitutions > 0 && stats.allocations > 0);
+        let (_, stats) = collect(|| substitute(&var("x"), "y", &var("z")));
+        assert_eq!(
+            stats,
+            Stats {
+                substitutions: 1,
+                renames: 0,
+                allocations: 1,
+                free_var_scans: 0
+            }
+        );
+        // Results are followed by the statistics with `--stats`
+        let config = Config::try_parse_from(["lambda", "--stats"]).unwrap();
+        let outcome = run_prog(
+            "(λx.x) a;".to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(
+            captured(),
+            ["a", "(0 substitutions, 0 renames, 0 allocations)"]
+        );
+        assert_eq!(outcome.stats, Some(Stats::default()));
+    }
+
+    #[test]
+    fn test_opaque_free_vars() {
+        let program = "(λx.λy.(f (g x) y)) (h a) (λz.z);";
+        let warnings = eval_prog(
+            program.to_string(),
+            &mut HashMap::new(),
+            &Config::default(),
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["((f (g (h a))) λz.z)"]);
+        assert_eq!(warnings.len(), 4);
+        let config = Config::try_parse_from(["lambda", "--opaque-free-vars"]).unwrap();
+        let warnings = eval_prog(
+            program.to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        assert_eq!(captured(), ["((f (g (h a))) λz.z)"]);
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_entry() {
+        let config =
+            Config::try_parse_from(["lambda", "-e", "id = λa.a;", "--entry", "id", "--", "x"])
+                .unwrap();
+        let mut env = HashMap::new();
+        eval_prog(
+            config.eval.clone().unwrap(),
+            &mut env,
+            &config,
+            PRINT_CAPTURE,
+        );
+        let entry = config.entry.as_deref().unwrap();
+        let (term, _) = run_entry(entry, &config.args, &mut env, &config, PRINT_CAPTURE).unwrap();
+        assert_eq!(captured(), ["x"]);
+        assert_eq!(term, var("x"));
+        // Arguments are applied from left to right
+        env.insert("K".to_string(), parse_term_str("λa.λb.a").unwrap());
+        let args = ["y".to_string(), "z".to_string()];
+        run_entry("K", &args, &mut env, &config, PRINT_CAPTURE).unwrap();
+        assert_eq!(captured(), ["y"]);
+        assert!(matches!(
+            run_entry("missing", &[], &mut env, &config, PRINT_CAPTURE),
+            Err(Error::UnboundVar(_))
+        ));
+    }
+
+    #[test]
+    fn test_latex_format() {
+        let latex = |src: &str| {
+            strip_color(&print::term_styled(
+                &parse_term_str(src).unwrap(),
+                Style::Latex,
+            ))
+        };
+        assert_eq!(latex("λx.x"), "\\lambda x. x");
+        assert_eq!(latex("λf.(f (g y))"), "\\lambda f. (f\\,(g\\,y))");
+        assert_eq!(
+            latex("λfoo.(foo x')"),
+            "\\lambda \\mathit{foo}. (\\mathit{foo}\\,x')"
+        );
+        let config = Config::try_parse_from(["lambda", "--format", "latex"]).unwrap();
+        assert_eq!(config.style, Style::Latex);
+    }
+
+    #[test]
+    fn test_is_normal_form_eta() {
+        let eta_redex = parse_term_str("λx.(f x)").unwrap();
+        assert!(is_normal_form(&eta_redex, Conversion::Beta));
+        assert!(!is_normal_form(&eta_redex, Conversion::BetaEta));
+        assert!(is_normal_form(&eta_reduce(&eta_redex), Conversion::BetaEta));
+        // The parameter must not be free in the function, and nested η-redexes count
+        assert!(is_normal_form(
+            &parse_term_str("λx.(x x)").unwrap(),
+            Conversion::BetaEta
+        ));
+        assert!(!is_normal_form(
+            &parse_term_str("g λy.(h y)").unwrap(),
+            Conversion::BetaEta
+        ));
+    }
+
+    #[test]
+    fn test_program_from_stdin() {
+        let config = Config::try_parse_from(["lambda", "-"]).unwrap();
+        let stdin = "I = λx.x;\nI (λy.y) z;".as_bytes();
+        let program = config.read_program(stdin).unwrap().unwrap();
+        eval_prog(program, &mut HashMap::new(), &config, PRINT_CAPTURE);
+        assert_eq!(captured(), ["z"]);
+        // Without a file or program there is nothing to read
+        assert!(Config::default().read_program("z".as_bytes()).is_none());
+        // Read errors name the file
+        let config = Config::try_parse_from(["lambda", "missing-program.lc"]).unwrap();
+        let err = config.read_program("z".as_bytes()).unwrap().unwrap_err();
+        assert!(err.to_string().starts_with("missing-program.lc: "));
+        let config = Config::try_parse_from(["lambda", "-"]).unwrap();
+        let err = config.read_program(&[0xff][..]).unwrap().unwrap_err();
+        assert!(err.to_string().starts_with("standard input: "));
+    }
+
+    #[test]
+    fn test_max_term_size() {
+        // Each step of `(λx.(x x x)) (λx.(x x x))` makes the term larger
+        let program = "(λx.(x x x)) (λx.(x x x));";
+        let config = Config::try_parse_from(["lambda", "--max-term-size", "100"]).unwrap();
+        let warnings = eval_prog(
+            program.to_string(),
+            &mut HashMap::new(),
+            &config,
+            PRINT_CAPTURE,
+        );
+        let interrupted = warnings
+            .iter()
+            .find(|w| w.kind == WarningKind::TermTooLarge);
+        assert_eq!(
+            interrupted.map(|w| w.message.as_str()),
+            Some("term grew larger than 100 nodes")
+        );
+        let result = parse_term_str(&captured()[0]).unwrap();
+        assert!(term_size(&result) > 100);
+        let mut interpreter = Interpreter::new(config);
+        assert!(matches!(
+            interpreter.eval(program),
+            Err(Error::TermTooLarge { limit: 100 })
+        ));
+    }
+
+    #[test]
+    fn test_redexes() {
+        let term = parse_term_str("f ((λx.x) a) (λy.((λz.z) b))").unwrap();
+        assert_eq!(
+            redexes(&term),
+            [vec![Dir::Fun, Dir::Arg], vec![Dir::Arg, Dir::Body]]
+        );
+        // Nested redexes are listed outermost first, and normal forms have none
+        let term = parse_term_str("(λx.x) ((λy.y) c)").unwrap();
+        assert_eq!(redexes(&term), [vec![], vec![Dir::Arg]]);
+        assert!(redexes(&parse_term_str("λx.(x x)").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_contract_at() {
+        let term = parse_term_str("f ((λx.x) a) (λy.((λz.z) b))").unwrap();
+        let paths = redexes(&term);
+        assert_eq!(
+            contract_at(&term, &paths[1]).unwrap(),
+            parse_term_str("f ((λx.x) a) (λy.b)").unwrap()
+        );
+        assert_eq!(
+            contract_at(&term, &paths[0]).unwrap(),
+            parse_term_str("f a (λy.((λz.z) b))").unwrap()
+        );
+        let invalid = contract_at(&term, &[Dir::Fun]);
+        assert!(matches!(invalid, Err(Error::NotARedex(path)) if path == [Dir::Fun]));
+        assert!(contract_at(&term, &[Dir::Body]).is_err());
+    }
+
+    #[test]
+    fn test_term_from_str() {
+        assert_eq!("λx.x".parse::<Term>().unwrap(), abs("x", var("x")));
+        assert_eq!("f a;".parse::<Term>().unwrap(), app(var("f"), var("a")));
+        assert!(matches!("λx.".parse::<Term>(), Err(Error::Parse(_))));
+        assert!("a; b".parse::<Term>().is_err());
+        assert!("x = y;".parse::<Term>().is_err());
+    }
+
+    #[test]
+    fn test_numeral_constants() {
+        let mut env = HashMap::new();
+        let program = "ten = 10; ten;";
+        let config = Config::try_parse_from(["lambda", "--decode"]).unwrap();
+        eval_prog(program.to_string(), &mut env, &config, PRINT_CAPTURE);
+        // The constant is stored as the literal and only expanded when reduced
+        assert_eq!(env["ten"], var("10"));
+        assert_eq!(captured(), ["10"]);
+        let mut env = HashMap::new();
+        let outcome = run_prog(
+            format!("{}\nten = 10; succ ten;", PRELUDE),
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+        );
+        assert_eq!(
+            outcome.value.as_ref().and_then(from_signed_numeral),
+            Some(11)
+        );
+        // Church numerals are printed as the constant with `--use-aliases`
+        let config = Config::try_parse_from(["lambda", "--use-aliases"]).unwrap();
+        let result = parse_term_str("λf.λx.(f (f (f (f (f (f (f (f (f (f x))))))))))").unwrap();
+        assert_eq!(strip_color(&print::result(&result, &env, &config)), "ten");
+    }
+
+    #[test]
+    fn test_complete() {
+        let env = HashMap::from([
+            ("factorial".to_string(), var("f")),
+            ("Fact".to_string(), var("f")),
+            ("id".to_string(), var("i")),
+        ]);
+        assert_eq!(complete("fac", 3, &env), (0, vec!["factorial".to_string()]));
+        // The word before the cursor is completed, including keywords
+        assert_eq!(
+            complete("(λx.x) im", 10, &env),
+            (8, vec!["import".to_string()])
+        );
+        assert_eq!(complete("f F", 3, &env), (2, vec!["Fact".to_string()]));
+    }
+
+    #[test]
+    fn test_fresh_names_deterministic() {
+        // Substitution renames every binder that would capture one of many free variables
+        let free = (0..50).map(|i| format!("x{}", i)).collect::<Vec<_>>();
+        let value = free.iter().fold(var("x"), |t, v| app(t, var(v)));
+        let body = free
+            .iter()
+            .rev()
+            .fold(app(var("y"), var("x")), |t, v| abs(v, t));
+        let renamed = || print::term_plain(&substitute(&abs("x", body.clone()), "y", &value));
+        let first = renamed();
+        assert_eq!(first, renamed());
+        assert!(first.starts_with("λx'.λx0'.λx1'."));
+        // Sets with the same names in another insertion order give the same name
+        let mut avoid = HashSet::new();
+        for name in free.iter().rev() {
+            avoid.insert(name.clone());
+        }
+        avoid.extend(["x0'".to_string(), "x0''".to_string()]);
+        let ordered = free
+            .iter()
+            .cloned()
+            .chain(["x0''".to_string(), "x0'".to_string()]);
+        assert_eq!(fresh_var("x0", &avoid), fresh_var("x0", &ordered.collect()));
+    }
+
+    #[test]
+    fn test_eval_open() {
+        let term = parse_term_str("(λx.(f x)) a").unwrap();
+        assert_eq!(eval_open(&term), parse_term_str("f a").unwrap());
+        // Free names, including numerals, are constants that are never unfolded
+        let term = parse_term_str("(λg.λy.(g (g y))) (λz.(plus z 1)) n").unwrap();
+        assert_eq!(
+            eval_open(&term),
+            parse_term_str("plus (plus n 1) 1").unwrap()
+        );
+        let term = parse_term_str("λy.((λx.λy.(x y)) y)").unwrap();
+        assert_eq!(eval_open(&term), parse_term_str("λy.λy'.(y y')").unwrap());
+    }
+
+    #[test]
+    fn test_flat_spines() {
+        let flat = |src: &str| strip_color(&print::term_flat(&parse_term_str(src).unwrap()));
+        assert_eq!(flat("((((f a) b) c) d)"), "f a b c d");
+        assert_eq!(flat("f (g x) (λy.y) z"), "f (g x) (λy.y) z");
+        assert_eq!(flat("(λx.(x x)) (h (k a) b)"), "(λx.(x x)) (h (k a) b)");
+        assert_eq!(flat("λx.λy.(x y)"), "λx.λy.(x y)");
+        // Flattened output parses back to the same left-nested structure
+        for src in [
+            "f a b c d",
+            "f (g x y) (λy.(y a b))",
+            "(λx.x) a ((b c) d)",
+            "x",
+        ] {
+            let term = parse_term_str(src).unwrap();
+            assert_eq!(parse_term_str(&flat(src)).unwrap(), term);
+        }
+        let config = Config::try_parse_from(["lambda", "--flat-spines"]).unwrap();
+        let term = parse_term_str("f a b").unwrap();
+        assert_eq!(
+            strip_color(&print::result(&term, &HashMap::new(), &config)),
+            "f a b"
+        );
+    }
+
+    #[test]
+    fn test_assert() {
+        let mut env = HashMap::new();
+        let program = format!(
+            "{}\nassert (Add 1) 1 == 2; assert Succ 0 == 1; assert (add 1 1) == 2;",
+            PRELUDE
+        );
+        let outcome = run_prog(program, &mut env, &Config::default(), PRINT_CAPTURE);
+        assert!(outcome.errors.is_empty());
+        assert!(captured().is_empty());
+        // A failing assertion is reported and fails the run, the rest still runs
+        let program = "assert (λx.x) a == b; c;".to_string();
+        let outcome = run_prog(program, &mut env, &Config::default(), PRINT_CAPTURE);
+        assert_eq!(captured(), ["c"]);
+        assert_eq!(outcome.errors.len(), 1);
+        assert_eq!(
+            outcome.errors[0].to_string(),
+            "assertion failed: `(λx.x a)` is not equal to `b`"
+        );
+    }
+
+    #[test]
+    fn test_env_graph_dot() {
+        let mut env = HashMap::new();
+        eval_prog(
+            "a = λx.x; b = a a; c = λy.(b y z);".to_string(),
+            &mut env,
+            &Config::default(),
+            PRINT_NONE,
+        );
+        let dot = env_graph_dot(&env);
+        assert!(dot.starts_with("digraph env {"));
+        assert!(dot.contains("\"b\" -> \"a\";"));
+        assert!(dot.contains("\"c\" -> \"b\";"));
+        assert!(dot.contains("    \"a\";"));
+        assert_eq!(dot.matches("->").count(), 2);
+    }
+
+    #[test]
+    fn test_type_lambda() {
+        let term = parse_term_str("Λt. λx. x").unwrap();
+        assert_eq!(print::term_plain(&term), "Λt.λx.x");
+        assert_eq!(strip_color(&print::term(&term)), "Λt.λx.x");
+        // The printed term parses back to the same term
+        assert_eq!(parse_term_str(&print::term_plain(&term)).unwrap(), term);
+        // The type parameter is bound like any other parameter
+        let applied = parse_term_str("(Λt.λx.(x t)) a").unwrap();
+        let value = eval_open(&applied);
+        assert_eq!(print::term_plain(&value), "λx.(x a)");
+    }
+
+    #[test]
+    fn test_reduce_head() {
+        let term = parse_term_str("(λy.λx.(y x ((λz.z) a))) (λw.w)").unwrap();
+        let whnf = reduce_bounded(&term, &HashMap::new(), Strategy::Cbn, 100).unwrap();
+        assert_eq!(print::term_plain(&whnf), "λx.((λw.w x) (λz.z a))");
+        let hnf = reduce_head(&term);
+        assert_eq!(print::term_plain(&hnf), "λx.(x (λz.z a))");
+        assert_eq!(print::term_plain(&eval_open(&term)), "λx.(x a)");
+        // A head normal form is left unchanged, even if its arguments diverge
+        let term = parse_term_str("λx.(x ((λy.(y y)) (λy.(y y))))").unwrap();
+        assert_eq!(reduce_head(&term), term);
+    }
+
+    #[test]
+    fn test_block_scope() {
+        let mut env = HashMap::new();
+        let program = format!(
+            "{}\nx = begin two = Succ 1; four = Mul two two; Add four two end;\nx;\ntwo;",
+            PRELUDE
+        );
+        let config = Config::default();
+        let outcome = run_prog(program, &mut env, &config, PRINT_CAPTURE);
+        assert!(outcome.errors.is_empty());
+        assert_eq!(captured(), ["two"]);
+        assert!(!env.contains_key("two"));
+        assert!(!env.contains_key("four"));
+        let value = reduce_to_normal_form(&env["x"], &env, &config, PRINT_NONE);
+        assert_eq!(from_church_numeral(&value), Some(6));
+        assert!(outcome
+            .warnings
+            .iter()
+            .any(|w| w.to_string().contains("`two` is not defined")));
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut env = HashMap::new();
+        let config = Config {
+            verify: true,
+            ..Config::default()
+        };
+        let program = format!("{}\nMul 2 (Succ 1);\n(λx.λy.x) a ((λz.z) b);", PRELUDE);
+        let warnings = eval_prog(program, &mut env, &config, PRINT_NONE);
+        assert!(warnings
+            .iter()
+            .all(|w| w.kind != WarningKind::StrategyMismatch));
+        // A reducer that skips the last step under applicative order is caught
+        let term = parse_term_str("(λx.λy.x) a ((λz.z) b)").unwrap();
+        let wrong = |term: &Term, env: &Env, strategy: Strategy, limit: usize| match strategy {
+            Strategy::Applicative => reduce_bounded(term, env, strategy, limit)
+                .map(|t| parse_term_str(&format!("({}) c", print::term_plain(&t))).unwrap()),
+            _ => reduce_bounded(term, env, strategy, limit),
+        };
+        let warning = verify_with(&term, &HashMap::new(), DEFAULT_STEP_LIMIT, wrong).unwrap();
+        assert_eq!(warning.kind, WarningKind::StrategyMismatch);
+        assert_eq!(
+            warning.message,
+            "normal order reduces to `a` but applicative order to `(a c)`"
+        );
+        assert_eq!(verify(&term, &HashMap::new(), DEFAULT_STEP_LIMIT), None);
+    }
+
+    #[test]
+    fn test_short_names() {
+        let term = parse_term_str("λfoo.λbar.(foo (λbaz.(baz a)) (λqux.(qux bar)))").unwrap();
+        let short = short_names(&term);
+        assert_eq!(print::term_plain(&short), "λb.λc.((b λd.(d a)) λd.(d c))");
+        let reparsed = parse_term_str(&print::term_plain(&short)).unwrap();
+        assert!(alpha_eq(&reparsed, &term));
+        // Past `z`, binders are named with capital letters
+        let deep = (0..30).fold(var("x0"), |body, i| abs(&format!("x{}", i), body));
+        let short = short_names(&deep);
+        assert!(!print::term_plain(&short).contains(|c: char| c.is_ascii_digit()));
+        assert!(alpha_eq(&short, &deep));
+    }
+
+    #[test]
+    fn test_definition_order() {
+        assert!(check_prelude(PRELUDE).is_ok());
+        let err = check_prelude("Twice = λf.λx.(f (f x));\nFour = Twice Double 1;\nDouble = λn.n;")
+            .unwrap_err();
+        assert!(matches!(err, Error::DefinitionOrder { .. }));
+        assert_eq!(err.to_string(), "`Four` uses `Double` before it is defined");
+        // Self references, redefinitions and names that are never defined are in order
+        let prog = parse_prog("F = λn.(F n); G = F x; F = G;").unwrap();
+        assert!(check_definition_order(&prog).is_ok());
+    }
+
+    #[test]
+    fn test_trace_html() {
+        let term = parse_term_str("(λx.λy.x) a b").unwrap();
+        let (steps, terminated) = trace_redexes(&term, &HashMap::new(), 100);
+        assert!(terminated);
+        let html = print::trace_html(&steps, terminated);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(html.matches("<section class=\"step\">").count(), 3);
+        assert!(html.contains("<pre>(<mark>(λx.λy.x a)</mark> b)</pre>"));
+        assert!(html.contains("<pre><mark>(λy.a b)</mark></pre>"));
+        assert!(html.contains("<h2>step 2, normal form</h2><pre>a</pre>"));
+        // A diverging term stops at the limit with every step still contracting a redex
+        let omega = parse_term_str("(λx.(x x)) (λx.(x x))").unwrap();
+        let (steps, terminated) = trace_redexes(&omega, &HashMap::new(), 4);
+        assert!(!terminated);
+        let html = print::trace_html(&steps, terminated);
+        assert_eq!(html.matches("<section class=\"step\">").count(), 5);
+        assert_eq!(html.matches("<mark>").count(), 4);
+        assert!(html.contains("Stopped before reaching a normal form."));
+    }
+
+    #[test]
+    fn test_project_config() {
+        let project =
+            "# defaults for this project\nstep_limit = 5\nstrategy = \"cbn\"\nno_prelude = true\n";
+        let options = Config::project_options(project).unwrap();
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let config = Config::try_parse_with_project(&options, args(&["lambda", "f.lc"])).unwrap();
+        assert_eq!(config.step_limit, Some(5));
+        assert_eq!(config.strategy, Strategy::Cbn);
+        assert!(config.no_prelude);
+        assert_eq!(config.file.as_deref(), Some("f.lc"));
+        // Flags override the project file
+        let config =
+            Config::try_parse_with_project(&options, args(&["lambda", "--step-limit", "7"]))
+                .unwrap();
+        assert_eq!(config.step_limit, Some(7));
+        assert_eq!(config.strategy, Strategy::Cbn);
+        // The limit interrupts evaluation
+        let mut env = HashMap::new();
+        let warnings = eval_prog(
+            "(λx.(x x)) (λx.(x x));".to_string(),
+            &mut env,
+            &config,
+            PRINT_NONE,
+        );
+        assert!(warnings.iter().any(|w| w.kind == WarningKind::StepLimit));
+        // A term normalized in exactly as many steps as the limit is not interrupted
+        let config = Config {
+            step_limit: Some(1),
+            ..Config::default()
+        };
+        let outcome = run_prog("(λx.x) a;".to_string(), &mut env, &config, PRINT_NONE);
+        assert_eq!(outcome.value, Some(var("a")));
+        assert!(outcome
+            .warnings
+            .iter()
+            .all(|w| w.kind != WarningKind::StepLimit));
+        let program = "(λx.x) ((λx.x) a);".to_string();
+        let outcome = run_prog(program, &mut env, &config, PRINT_NONE);
+        assert_eq!(outcome.value, Some(parse_term_str("(λx.x) a").unwrap()));
+        assert!(outcome
+            .warnings
+            .iter()
+            .any(|w| w.kind == WarningKind::StepLimit));
+        let err = Config::project_options("step_limit = 5\nsteps = 3").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unknown option `steps`");
+        // The limit also bounds assertions and `--verify`
+        let program = format!("{}\nassert (Add 2) 2 == 4;", PRELUDE);
+        let config = Config {
+            step_limit: Some(2),
+            ..Config::default()
+        };
+        let outcome = run_prog(program.clone(), &mut HashMap::new(), &config, PRINT_NONE);
+        assert_eq!(outcome.errors.len(), 1);
+        let outcome = run_prog(program, &mut HashMap::new(), &Config::default(), PRINT_NONE);
+        assert!(outcome.errors.is_empty());
+        let term = parse_term_str("(λx.x) ((λx.x) ((λx.x) a))").unwrap();
+        let wrong: ReduceFn = |term, env, strategy, limit| match strategy {
+            Strategy::Applicative => Ok(app(reduce_bounded(term, env, strategy, limit)?, var("c"))),
+            _ => reduce_bounded(term, env, strategy, limit),
+        };
+        assert_eq!(verify_with(&term, &HashMap::new(), 2, wrong), None);
+        assert!(verify_with(&term, &HashMap::new(), 3, wrong).is_some());
+    }
+
+    #[test]
+    fn test_substitute_scans_value_once() {
+        // λa0.λa1...λa9.(x a0), where substituting `a9` must rename the innermost binder
+        let term = (0..10).rev().fold(app(var("x"), var("a0")), |body, i| {
+            abs(&format!("a{}", i), body)
+        });
+        let (result, stats) = collect(|| substitute(&term, "x", &var("a9")));
+        assert_eq!(stats.free_var_scans, 1);
+        assert_eq!(stats.renames, 1);
+        let expected = (0..10).rev().fold(app(var("a9"), var("a0")), |body, i| {
+            abs(
+                &if i == 9 {
+                    "a9'".to_string()
+                } else {
+                    format!("a{}", i)
+                },
+                body,
+            )
+        });
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_profile() {
+        let config = Config {
+            profile: true,
+            ..Config::default()
+        };
+        let program = format!("{}\nMul 10 10;", PRELUDE);
+        let start = std::time::Instant::now();
+        let outcome = run_prog(program, &mut HashMap::new(), &config, PRINT_NONE);
+        let elapsed = start.elapsed();
+        let profile = outcome.profile.unwrap();
+        assert!(profile.parsing > std::time::Duration::ZERO);
+        assert!(profile.inlining > std::time::Duration::ZERO);
+        assert!(profile.reduction > std::time::Duration::ZERO);
+        assert!(profile.parsing + profile.inlining + profile.reduction <= elapsed);
+        let printed = profile.to_string();
+        for phase in ["parsing ", "inlining ", "reduction "] {
+            assert!(printed.contains(phase));
+        }
+        // Nothing is measured without `--profile`
+        let outcome = run_prog(
+            "a;".to_string(),
+            &mut HashMap::new(),
+            &Config::default(),
+            PRINT_NONE,
+        );
+        assert_eq!(outcome.profile, None);
+    }
+
+    #[test]
+    fn test_project_config_conflicts() {
+        let parse = |project: &str, args: &[&str]| {
+            let options = Config::project_options(project).unwrap();
+            let args = ["lambda"].iter().chain(args).map(|a| a.to_string());
+            Config::try_parse_with_project(&options, args).unwrap()
+        };
+        // Flags given on the command line win over project options they conflict with
+        let config = parse("quiet = true", &["--verbose"]);
+        assert!(config.verbose && !config.quiet);
+        let config = parse("strategy = \"cbv\"", &["--canonical"]);
+        assert!(config.canonical);
+        assert_eq!(config.strategy, Strategy::default());
+        let config = parse(
+            "prelude_path = \"lib.lc\"\nstep_limit = 9",
+            &["--no-prelude"],
+        );
+        assert!(config.no_prelude);
+        assert_eq!(config.prelude_path, None);
+        assert_eq!(config.step_limit, Some(9));
+    }
+
+    #[test]
+    fn test_rewrite_rules() {
+        let mut env = HashMap::new();
+        let config = Config::default();
+        eval_prog(PRELUDE.to_string(), &mut env, &config, PRINT_NONE);
+        // Without the rule, `Add` is unfolded and applied to the free `x`
+        let outcome = run_prog("Add 0 x;".to_string(), &mut env, &config, PRINT_NONE);
+        assert_ne!(outcome.value, Some(var("x")));
+        // The rule fires before `Add` is unfolded
+        let program = "rule (Add 0 ?n) => ?n; Add 0 x;".to_string();
+        let outcome = run_prog(program, &mut env, &config, PRINT_NONE);
+        assert_eq!(outcome.value, Some(var("x")));
+        // It fires under binders of other names, but not on a bound `Add`
+        let program = "rule (Add 0 ?n) => ?n; λy.(Add 0 (Mul y y));".to_string();
+        let outcome = run_prog(program, &mut env, &config, PRINT_NONE);
+        let plain = run_prog("λy.(Mul y y);".to_string(), &mut env, &config, PRINT_NONE);
+        assert!(alpha_eq(&outcome.value.unwrap(), &plain.value.unwrap()));
+        let rule = RewriteRule::parse("Add 0 ?n", "?n").unwrap();
+        let term = parse_term_str("λAdd.(Add 0 x)").unwrap();
+        assert_eq!(rewrite_step(&term, &[rule]), None);
+        let rules = [RewriteRule::parse("f ?x", "?x").unwrap()];
+        let term = parse_term_str("λf.(f a)").unwrap();
+        assert_eq!(rewrite_step(&term, &rules), None);
+        let term = parse_term_str("λg.(f g)").unwrap();
+        let rewritten = rewrite_step(&term, &rules);
+        assert_eq!(rewritten, Some(parse_term_str("λg.g").unwrap()));
+        // Filling in a hole does not capture the binders of the right side
+        let rule = RewriteRule::parse("f ?y", "λx.?y").unwrap();
+        let rewritten = rewrite_step(&parse_term_str("g (f x)").unwrap(), &[rule]);
+        assert_eq!(rewritten, Some(parse_term_str("g (λx'.x)").unwrap()));
+        let err = RewriteRule::parse("f ?x", "?z").unwrap_err();
+        assert_eq!(err.to_string(), "`?z` is not defined");
+    }
 }
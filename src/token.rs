@@ -0,0 +1,103 @@
+use std::ops::Range;
+
+/// Kinds of lexical tokens in a program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// `λ`, `\` or the type abstraction `Λ`
+    Lambda,
+    Dot,
+    /// `->` in `\x -> body`
+    Arrow,
+    LParen,
+    RParen,
+    Ident,
+    /// One of the `KEYWORDS`, such as `import` or `macro`
+    Keyword,
+    Number,
+    Equals,
+    Semicolon,
+    Comma,
+    /// Infix operator such as `+`, or the explicit application operator `@`
+    Operator,
+    /// Quoted path in an import
+    String,
+    /// `-- comment` up to the end of the line
+    Comment,
+    /// Character that cannot start any token
+    Unknown,
+}
+
+/// A token and the byte range of the input it spans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Range<usize>,
+}
+
+/// Words reserved by the grammar
+pub const KEYWORDS: [&str; 9] = [
+    "and", "assert", "begin", "end", "import", "macro", "infixl", "infixr", "rule",
+];
+
+fn is_operator(c: char) -> bool {
+    "+-*/<>&|^%!?~$:".contains(c)
+}
+
+/// Split input into tokens without parsing it, for syntax highlighting
+/// Never fails, so incomplete or invalid programs are still tokenized.
+/// Whitespace is skipped and characters that start no token become `Unknown`.
+pub fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        // Consume characters while `pred` holds, returning the end of the token
+        let mut take_while = |pred: &dyn Fn(char) -> bool| {
+            while chars.next_if(|&(_, c)| pred(c)).is_some() {}
+            chars.peek().map_or(input.len(), |&(i, _)| i)
+        };
+        let rest = &input[start..];
+        let (kind, end) = match c {
+            _ if c.is_whitespace() => continue,
+            '-' if rest.starts_with("--") => (TokenKind::Comment, take_while(&|c| c != '\n')),
+            '-' if rest.starts_with("->") => {
+                chars.next();
+                (TokenKind::Arrow, start + 2)
+            }
+            'λ' | 'Λ' | '\\' => (TokenKind::Lambda, start + c.len_utf8()),
+            '.' => (TokenKind::Dot, start + 1),
+            '(' => (TokenKind::LParen, start + 1),
+            ')' => (TokenKind::RParen, start + 1),
+            '=' => (TokenKind::Equals, start + 1),
+            ';' => (TokenKind::Semicolon, start + 1),
+            ',' => (TokenKind::Comma, start + 1),
+            '@' => (TokenKind::Operator, start + 1),
+            '"' => {
+                let end = take_while(&|c| c != '"');
+                // Include the closing quote unless the string is unterminated
+                match chars.next() {
+                    Some((i, _)) => (TokenKind::String, i + 1),
+                    None => (TokenKind::String, end),
+                }
+            }
+            _ if is_operator(c) => (TokenKind::Operator, take_while(&is_operator)),
+            _ if c.is_ascii_alphanumeric() => {
+                let end = take_while(&|c| c.is_ascii_alphanumeric() || c == '_' || c == '\'');
+                let word = &input[start..end];
+                let kind = if KEYWORDS.contains(&word) {
+                    TokenKind::Keyword
+                } else if word.chars().all(|c| c.is_ascii_digit()) {
+                    TokenKind::Number
+                } else {
+                    TokenKind::Ident
+                };
+                (kind, end)
+            }
+            _ => (TokenKind::Unknown, start + c.len_utf8()),
+        };
+        tokens.push(Token {
+            kind,
+            span: start..end,
+        });
+    }
+    tokens
+}
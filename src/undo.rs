@@ -0,0 +1,32 @@
+use crate::eval::Env;
+
+/// Stack of environment snapshots used to revert definitions in the REPL
+#[derive(Debug, Default)]
+pub struct UndoStack {
+    snapshots: Vec<Env>,
+}
+
+impl UndoStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the environment `before` a command if the command changed it
+    pub fn record(&mut self, before: Env, env: &Env) {
+        if &before != env {
+            self.snapshots.push(before);
+        }
+    }
+
+    /// Restore the environment to before the last recorded change
+    /// Returns `false` if there is nothing left to undo.
+    pub fn undo(&mut self, env: &mut Env) -> bool {
+        match self.snapshots.pop() {
+            Some(before) => {
+                *env = before;
+                true
+            }
+            None => false,
+        }
+    }
+}
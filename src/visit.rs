@@ -0,0 +1,35 @@
+use crate::parser::Term;
+
+/// Visit every subterm of a term in pre-order, the term itself first
+/// and then the body of an abstraction or the function before the argument of an application.
+///
+/// Traverses the term with an explicit stack instead of recursion,
+/// so that very deep terms do not overflow the call stack.
+pub fn walk(term: &Term, visit: &mut dyn FnMut(&Term)) {
+    let mut stack = vec![term];
+    while let Some(term) = stack.pop() {
+        visit(term);
+        match term {
+            Term::Variable(_) => {}
+            Term::Abstraction(_, body) => stack.push(body),
+            Term::Application(f, x) => {
+                stack.push(x);
+                stack.push(f);
+            }
+        }
+    }
+}
+
+/// Transform a term bottom-up
+/// The subterms of every node are transformed before `f` is applied to the rebuilt node.
+pub fn map(term: &Term, f: &mut dyn FnMut(Term) -> Term) -> Term {
+    let term = match term {
+        Term::Variable(_) => term.clone(),
+        Term::Abstraction(param, body) => Term::Abstraction(param.clone(), Box::new(map(body, f))),
+        Term::Application(e1, e2) => {
+            let e1 = map(e1, f);
+            Term::Application(Box::new(e1), Box::new(map(e2, f)))
+        }
+    };
+    f(term)
+}
@@ -0,0 +1,55 @@
+use std::fmt;
+
+use crate::config::Config;
+
+/// Kinds of diagnostics that do not stop evaluation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A free variable is not defined in the environment
+    UnboundVariable,
+    /// An assignment replaces an existing definition
+    Shadowing,
+    /// A term looks like it might not terminate
+    MightDiverge,
+    /// Evaluation was interrupted by the time limit
+    Timeout,
+    /// Evaluation was interrupted by the step limit
+    StepLimit,
+    /// Evaluation was interrupted by the term size limit
+    TermTooLarge,
+    /// Normal and applicative order reduction disagree on a normal form, see `--verify`
+    StrategyMismatch,
+    /// A numeral literal is larger than `NUMERAL_CAP`
+    NumeralCap,
+}
+
+/// A diagnostic collected while running a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+impl Warning {
+    pub fn new(kind: WarningKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Warning: {}", self.message)
+    }
+}
+
+/// Warnings of a run that are printed to the user, which are none with `--quiet`
+/// They are still collected, so callers can inspect them.
+pub fn reported<'a>(warnings: &'a [Warning], config: &Config) -> &'a [Warning] {
+    match config.quiet {
+        true => &[],
+        false => warnings,
+    }
+}